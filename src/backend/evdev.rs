@@ -0,0 +1,350 @@
+use super::{InputBackend, InputDecision};
+use crate::diagnostics::diag;
+use crate::virtual_key::{InputKey, KeyCode, KeyOpt, WHEEL_NOTCH};
+use std::collections::HashSet;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read};
+use std::os::fd::AsRawFd;
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+const EV_SYN: u16 = 0x00;
+const EV_KEY: u16 = 0x01;
+const EV_REL: u16 = 0x02;
+const SYN_REPORT: u16 = 0x00;
+const SYN_DROPPED: u16 = 0x03;
+const REL_HWHEEL: u16 = 0x06;
+const REL_WHEEL: u16 = 0x08;
+const KEY_MAX: usize = 0x2ff;
+const EVIOCGKEY: libc::Ioctl = libc::_IOR::<[u8; KEY_MAX / 8 + 1]>(b'E' as u32, 0x18);
+const INPUT_EVENT_SIZE: usize = std::mem::size_of::<libc::input_event>();
+
+/// Listens by reading `input_event`s from `/dev/input/event*`.
+///
+/// The directory is rescanned every `rescan_interval`, so devices plugged in after
+/// `install` are picked up, and devices that went away are dropped. Reading the
+/// event devices usually requires root or membership of the `input` group.
+/// The devices are not grabbed, so events can't be consumed, and remaps and consuming
+/// bindings fail with `Error::ConsumeUnsupported`. Only relative motion is
+/// reported, so there are no `KeyOpt::Move` events or cursor positions. Keys are
+/// reported by position, so `InputKey::key` is the same as `InputKey::physical_key`.
+/// When the kernel drops events of a device because they weren't read in time, the
+/// keys pressed or released meanwhile are reported once the device catches up.
+pub struct EvdevBackend {
+    devices_dir: Option<PathBuf>,
+    rescan_interval: Duration,
+    last_scan: Option<Instant>,
+    devices: Vec<EvdevDevice>,
+    failed_paths: HashSet<PathBuf>, // failed to open, logged once until they open or go away
+}
+
+struct EvdevDevice {
+    path: PathBuf,
+    file: File,
+    buffer: Vec<u8>,
+    finished: bool,
+    down_codes: HashSet<u16>, // keys reported as down, to resync after `SYN_DROPPED`
+    dropping: bool,           // discarding events up to the next `SYN_REPORT`
+}
+
+impl EvdevBackend {
+    pub fn new() -> Self {
+        Self::with_devices_dir("/dev/input")
+    }
+
+    /// watches `event*` files in `devices_dir` instead of `/dev/input`
+    pub fn with_devices_dir(devices_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            devices_dir: Some(devices_dir.into()),
+            rescan_interval: Duration::from_secs(1),
+            last_scan: None,
+            devices: Vec::new(),
+            failed_paths: HashSet::new(),
+        }
+    }
+
+    /// replays a file of raw `input_event`s, e.g. captured with `cat /dev/input/event3 > file`
+    pub fn from_recording(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+        let device = EvdevDevice::open(path, false)?;
+        Ok(Self {
+            devices_dir: None,
+            rescan_interval: Duration::MAX,
+            last_scan: None,
+            devices: vec![device],
+            failed_paths: HashSet::new(),
+        })
+    }
+
+    pub fn set_rescan_interval(&mut self, rescan_interval: Duration) {
+        self.rescan_interval = rescan_interval;
+    }
+
+    fn rescan_devices(&mut self) -> io::Result<()> {
+        let devices_dir = match &self.devices_dir {
+            Some(devices_dir) => devices_dir,
+            None => return Ok(()),
+        };
+        if let Some(last_scan) = self.last_scan {
+            if last_scan.elapsed() < self.rescan_interval {
+                return Ok(());
+            }
+        }
+        self.last_scan = Some(Instant::now());
+
+        let mut present_paths = HashSet::new();
+        for entry in fs::read_dir(devices_dir)? {
+            let path = entry?.path();
+            let is_event_device = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with("event"));
+            if is_event_device {
+                present_paths.insert(path);
+            }
+        }
+        self.devices
+            .retain(|device| present_paths.contains(&device.path));
+        self.failed_paths
+            .retain(|path| present_paths.contains(path));
+        for path in present_paths {
+            if self.devices.iter().any(|device| device.path == path) {
+                continue;
+            }
+            // udev sets the group and ACL of a hot-plugged device only after its node
+            // appears, so a device that failed to open is retried on every rescan
+            match EvdevDevice::open(&path, true) {
+                Ok(device) => {
+                    self.failed_paths.remove(&path);
+                    self.devices.push(device);
+                }
+                Err(e) => {
+                    if !self.failed_paths.contains(&path) {
+                        diag!(warn, "open input device:{} failed, {}", path.display(), e);
+                        self.failed_paths.insert(path);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Default for EvdevBackend {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl InputBackend for EvdevBackend {
+    fn install(&mut self) -> io::Result<()> {
+        self.last_scan = None;
+        self.rescan_devices()
+    }
+
+    fn poll(&mut self, on_input_key: &mut dyn FnMut(InputKey) -> InputDecision) -> io::Result<()> {
+        self.rescan_devices()?;
+        let mut delivered = false;
+        self.devices
+            .retain_mut(|device| match device.read_events() {
+                Ok(input_keys) => {
+                    delivered |= !input_keys.is_empty();
+                    // the devices aren't grabbed, so every event reaches other readers anyway
+                    for input_key in input_keys {
+                        on_input_key(input_key);
+                    }
+                    true
+                }
+                Err(e) => {
+                    // unplugged devices fail with ENODEV
+                    diag!(
+                        info,
+                        "read input device:{} failed, {}",
+                        device.path.display(),
+                        e
+                    );
+                    false
+                }
+            });
+        if !delivered {
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        Ok(())
+    }
+
+    fn now(&self) -> u32 {
+        // event devices stamp events with the realtime clock by default
+        let since_epoch = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default();
+        since_epoch.as_millis() as u32
+    }
+
+    fn uninstall(&mut self) {
+        self.devices.clear();
+        self.failed_paths.clear();
+    }
+}
+
+impl EvdevDevice {
+    fn open(path: &Path, non_blocking: bool) -> io::Result<Self> {
+        let mut options = OpenOptions::new();
+        options.read(true);
+        if non_blocking {
+            options.custom_flags(libc::O_NONBLOCK);
+        }
+        Ok(Self {
+            path: path.to_path_buf(),
+            file: options.open(path)?,
+            buffer: Vec::new(),
+            finished: false,
+            down_codes: HashSet::new(),
+            dropping: false,
+        })
+    }
+
+    fn read_events(&mut self) -> io::Result<Vec<InputKey>> {
+        let mut chunk = [0u8; INPUT_EVENT_SIZE * 64];
+        while !self.finished {
+            match self.file.read(&mut chunk) {
+                // only regular files, such as recordings, ever reach the end
+                Ok(0) => self.finished = true,
+                Ok(len) => self.buffer.extend_from_slice(&chunk[..len]),
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            }
+        }
+        let complete_len = self.buffer.len() - self.buffer.len() % INPUT_EVENT_SIZE;
+        let events: Vec<libc::input_event> = self.buffer[..complete_len]
+            .chunks_exact(INPUT_EVENT_SIZE)
+            .map(|bytes| unsafe { std::ptr::read_unaligned(bytes.as_ptr() as *const _) })
+            .collect();
+        self.buffer.drain(..complete_len);
+        let mut input_keys = Vec::new();
+        for event in events {
+            input_keys.append(&mut self.on_event(&event));
+        }
+        Ok(input_keys)
+    }
+
+    /// `SYN_DROPPED` means the kernel's buffer overflowed, so the events up to the next
+    /// `SYN_REPORT` are incomplete and discarded, and the key state is queried instead
+    fn on_event(&mut self, event: &libc::input_event) -> Vec<InputKey> {
+        if event.type_ == EV_SYN && event.code == SYN_DROPPED {
+            diag!(debug, "input device:{} dropped events", self.path.display());
+            self.dropping = true;
+            return Vec::new();
+        }
+        if self.dropping {
+            if event.type_ == EV_SYN && event.code == SYN_REPORT {
+                self.dropping = false;
+                return self.resync_keys(get_time(event));
+            }
+            return Vec::new();
+        }
+        if event.type_ == EV_KEY {
+            match event.value {
+                0 => {
+                    self.down_codes.remove(&event.code);
+                }
+                1 | 2 => {
+                    self.down_codes.insert(event.code);
+                }
+                _ => (),
+            }
+        }
+        input_keys_from_event(event)
+    }
+
+    /// releases the keys released while events were dropped, then presses those pressed
+    fn resync_keys(&mut self, time: u32) -> Vec<InputKey> {
+        let down_codes = match self.get_down_codes() {
+            Ok(down_codes) => down_codes,
+            Err(e) => {
+                // e.g. recordings, which have no state, so nothing is taken as held
+                diag!(
+                    info,
+                    "get key state of input device:{} failed, {}",
+                    self.path.display(),
+                    e
+                );
+                HashSet::new()
+            }
+        };
+        let mut released_codes: Vec<u16> =
+            self.down_codes.difference(&down_codes).copied().collect();
+        let mut pressed_codes: Vec<u16> =
+            down_codes.difference(&self.down_codes).copied().collect();
+        released_codes.sort_unstable();
+        pressed_codes.sort_unstable();
+        self.down_codes = down_codes;
+        let released_keys = released_codes
+            .into_iter()
+            .map(|code| input_key_from_code(code, KeyOpt::Up, time));
+        let pressed_keys = pressed_codes
+            .into_iter()
+            .map(|code| input_key_from_code(code, KeyOpt::Down, time));
+        released_keys.chain(pressed_keys).collect()
+    }
+
+    fn get_down_codes(&self) -> io::Result<HashSet<u16>> {
+        let mut key_bits = [0u8; KEY_MAX / 8 + 1];
+        if unsafe { libc::ioctl(self.file.as_raw_fd(), EVIOCGKEY, key_bits.as_mut_ptr()) } < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let down_codes = (0..=KEY_MAX)
+            .filter(|code| key_bits[code / 8] & (1 << (code % 8)) != 0)
+            .map(|code| code as u16)
+            .collect();
+        Ok(down_codes)
+    }
+}
+
+fn get_time(event: &libc::input_event) -> u32 {
+    (event.time.tv_sec as u64)
+        .wrapping_mul(1000)
+        .wrapping_add(event.time.tv_usec as u64 / 1000) as u32
+}
+
+fn input_keys_from_event(event: &libc::input_event) -> Vec<InputKey> {
+    let time = get_time(event);
+    if event.type_ == EV_REL {
+        let key = match (event.code, event.value > 0) {
+            (REL_WHEEL, true) => KeyCode::WheelUp,
+            (REL_WHEEL, false) => KeyCode::WheelDown,
+            (REL_HWHEEL, true) => KeyCode::WheelRight,
+            (REL_HWHEEL, false) => KeyCode::WheelLeft,
+            _ => return Vec::new(),
+        };
+        if event.value == 0 {
+            return Vec::new();
+        }
+        // the wheel has no release, so a notch is reported as a tap
+        let mut down_key = InputKey::new(key, KeyOpt::Down, time);
+        down_key.wheel_delta = event.value.saturating_abs().saturating_mul(WHEEL_NOTCH);
+        let up_key = InputKey {
+            opt: KeyOpt::Up,
+            ..down_key
+        };
+        return vec![down_key, up_key];
+    }
+    if event.type_ != EV_KEY {
+        return Vec::new();
+    }
+    let opt = match event.value {
+        0 => KeyOpt::Up,
+        // 2 is auto-repeat, reported as another down like on Windows
+        1 | 2 => KeyOpt::Down,
+        _ => return Vec::new(),
+    };
+    vec![input_key_from_code(event.code, opt, time)]
+}
+
+fn input_key_from_code(code: u16, opt: KeyOpt, time: u32) -> InputKey {
+    // evdev codes are positions, the layout is only applied by the display server
+    let mut input_key = InputKey::new(KeyCode::from_evdev_code(code as u32), opt, time);
+    input_key.scan_code = code as u32;
+    input_key
+}
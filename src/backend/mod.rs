@@ -0,0 +1,110 @@
+#[cfg(target_os = "linux")]
+pub(crate) mod evdev;
+pub(crate) mod recording;
+pub(crate) mod scripted;
+#[cfg(target_os = "linux")]
+pub(crate) mod uinput;
+#[cfg(windows)]
+pub(crate) mod win32;
+
+use crate::virtual_key::{CursorPos, InputKey, KeyCode, KeyOpt};
+use std::io;
+
+#[cfg(target_os = "linux")]
+pub use evdev::EvdevBackend;
+pub use recording::RecordingBackend;
+pub use scripted::{Script, ScriptedBackend, ScriptedInput};
+#[cfg(target_os = "linux")]
+pub use uinput::{UinputBackend, UINPUT_DEVICE_NAME};
+#[cfg(windows)]
+pub use win32::{Win32Backend, Win32SimulateBackend};
+
+/// What a backend should do with an input event, as decided by `on_input_key`.
+#[derive(PartialEq, Clone, Debug)]
+pub enum InputDecision {
+    /// let the event through to other applications
+    Pass,
+    /// swallow the event
+    Consume,
+    /// swallow the event, and inject these events instead, in order
+    Replace(Vec<InputKey>),
+}
+
+/// A source of `InputKey` events, driven by the listener thread.
+///
+/// `install` and `uninstall` are called on the listener thread, before the first
+/// and after the last `poll`. Each `poll` hands every pending event to `on_input_key`
+/// and may block for a short while when there is nothing to deliver. Backends that
+/// can hold events back apply the returned `InputDecision` before releasing them,
+/// and say so through `can_consume`; the others ignore it.
+///
+/// `uninstall` isn't called when `install` fails, so a failed `install` undoes
+/// whatever it installed so far.
+pub trait InputBackend: Send {
+    fn install(&mut self) -> io::Result<()>;
+
+    /// whether `InputDecision::Consume` and `InputDecision::Replace` keep events from
+    /// other applications, which remaps and consuming bindings depend on
+    fn can_consume(&self) -> bool {
+        false
+    }
+
+    fn poll(&mut self, on_input_key: &mut dyn FnMut(InputKey) -> InputDecision) -> io::Result<()>;
+
+    /// injects events that were swallowed earlier, without handing them to `poll` again
+    fn replay(&mut self, _input_keys: &[InputKey]) -> io::Result<()> {
+        // backends that never swallow have nothing to replay
+        Ok(())
+    }
+
+    /// the current time, in milliseconds on the same clock as `InputKey::time`
+    fn now(&self) -> u32;
+
+    fn uninstall(&mut self);
+}
+
+/// A synthetic input event, as sent by the `Simulator`.
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum SimulatedEvent {
+    /// `opt` is either `KeyOpt::Down` or `KeyOpt::Up`, mouse buttons included
+    Key {
+        key: KeyCode,
+        opt: KeyOpt,
+    },
+    MouseMove(CursorPos),
+    /// in wheel notches, positive is up for `delta_y` and right for `delta_x`
+    Scroll {
+        delta_x: i32,
+        delta_y: i32,
+    },
+}
+
+/// A sink for synthetic input events.
+///
+/// Every call to `send` gets the events of one `Simulator` operation, which a
+/// backend should deliver without interleaving them with other input when it can.
+pub trait SimulateBackend: Send {
+    fn send(&mut self, events: &[SimulatedEvent]) -> io::Result<()>;
+}
+
+/// The backend used by `start_listen` on the current platform.
+#[cfg(windows)]
+pub fn default_backend() -> Box<dyn InputBackend> {
+    Box::new(Win32Backend::new())
+}
+
+#[cfg(target_os = "linux")]
+pub fn default_backend() -> Box<dyn InputBackend> {
+    Box::new(EvdevBackend::new())
+}
+
+/// The backend used by `Simulator::new` on the current platform.
+#[cfg(windows)]
+pub fn default_simulate_backend() -> io::Result<Box<dyn SimulateBackend>> {
+    Ok(Box::new(Win32SimulateBackend::new()))
+}
+
+#[cfg(target_os = "linux")]
+pub fn default_simulate_backend() -> io::Result<Box<dyn SimulateBackend>> {
+    Ok(Box::new(UinputBackend::new()?))
+}
@@ -0,0 +1,33 @@
+use super::{SimulateBackend, SimulatedEvent};
+use std::io;
+use std::sync::{Arc, Mutex};
+
+/// Captures what would have been sent instead of sending it.
+///
+/// Clones share the captured events, so keep one clone and hand another to the `Simulator`.
+#[derive(Clone, Default)]
+pub struct RecordingBackend {
+    events: Arc<Mutex<Vec<SimulatedEvent>>>,
+}
+
+impl RecordingBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get_events(&self) -> Vec<SimulatedEvent> {
+        self.events.lock().unwrap().clone()
+    }
+
+    /// returns the captured events and starts capturing from scratch
+    pub fn take_events(&self) -> Vec<SimulatedEvent> {
+        std::mem::take(&mut *self.events.lock().unwrap())
+    }
+}
+
+impl SimulateBackend for RecordingBackend {
+    fn send(&mut self, events: &[SimulatedEvent]) -> io::Result<()> {
+        self.events.lock().unwrap().extend_from_slice(events);
+        Ok(())
+    }
+}
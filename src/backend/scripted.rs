@@ -0,0 +1,196 @@
+use super::{InputBackend, InputDecision};
+use crate::virtual_key::{CursorPos, InputKey, KeyCode, KeyOpt, WHEEL_NOTCH};
+use std::collections::VecDeque;
+use std::io;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// A list of timestamped input events, built up step by step.
+///
+/// Every event is stamped with the script's current time, which starts at 0 and
+/// only moves forward through `wait`.
+#[derive(Clone, Default)]
+pub struct Script {
+    input_keys: Vec<InputKey>,
+    now: u32,
+}
+
+impl Script {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn down(mut self, key: KeyCode) -> Self {
+        self.input_keys
+            .push(InputKey::new(key, KeyOpt::Down, self.now));
+        self
+    }
+
+    pub fn up(mut self, key: KeyCode) -> Self {
+        self.input_keys
+            .push(InputKey::new(key, KeyOpt::Up, self.now));
+        self
+    }
+
+    /// `down` followed by `up`
+    pub fn tap(self, key: KeyCode) -> Self {
+        self.down(key).up(key)
+    }
+
+    /// a notch of a wheel key, reported as a tap like the backends do
+    pub fn wheel(mut self, key: KeyCode) -> Self {
+        let mut down_key = InputKey::new(key, KeyOpt::Down, self.now);
+        down_key.wheel_delta = WHEEL_NOTCH;
+        self.input_keys.push(down_key);
+        self.input_keys.push(InputKey {
+            opt: KeyOpt::Up,
+            ..down_key
+        });
+        self
+    }
+
+    pub fn move_to(mut self, pos: CursorPos) -> Self {
+        let mut input_key = InputKey::new(KeyCode::Unknown(0), KeyOpt::Move, self.now);
+        input_key.pos = Some(pos);
+        self.input_keys.push(input_key);
+        self
+    }
+
+    /// presses `modifer_keys` in order, taps `key`, then releases `modifer_keys` in reverse order
+    pub fn chord(mut self, modifer_keys: &[KeyCode], key: KeyCode) -> Self {
+        for modifer_key in modifer_keys {
+            self = self.down(*modifer_key);
+        }
+        self = self.tap(key);
+        for modifer_key in modifer_keys.iter().rev() {
+            self = self.up(*modifer_key);
+        }
+        self
+    }
+
+    pub fn wait(mut self, millis: u32) -> Self {
+        self.now = self.now.wrapping_add(millis);
+        self
+    }
+
+    /// appends an event as is; the script time moves to the event's time
+    pub fn push(mut self, input_key: InputKey) -> Self {
+        self.now = input_key.time;
+        self.input_keys.push(input_key);
+        self
+    }
+
+    pub fn get_now(&self) -> u32 {
+        self.now
+    }
+
+    pub fn input_keys(&self) -> &[InputKey] {
+        &self.input_keys
+    }
+}
+
+/// A handle to feed events into a `ScriptedBackend` from another owner or thread.
+///
+/// The backend's clock is the time of the last script or event fed, so a script
+/// ending with `wait` lets time pass after its last event. The events that other
+/// applications would see, after consuming and replaying, are kept for `take_passed`.
+#[derive(Clone, Default)]
+pub struct ScriptedInput {
+    queue: Arc<Mutex<VecDeque<InputKey>>>,
+    now: Arc<AtomicU32>,
+    passed: Arc<Mutex<Vec<InputKey>>>,
+}
+
+impl ScriptedInput {
+    pub fn feed(&self, script: &Script) {
+        self.queue
+            .lock()
+            .unwrap()
+            .extend(script.input_keys().iter().copied());
+        self.now.store(script.get_now(), Ordering::SeqCst);
+    }
+
+    pub fn feed_input_key(&self, input_key: InputKey) {
+        self.queue.lock().unwrap().push_back(input_key);
+        self.now.store(input_key.time, Ordering::SeqCst);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queue.lock().unwrap().is_empty()
+    }
+
+    /// takes the events let through so far
+    pub fn take_passed(&self) -> Vec<InputKey> {
+        std::mem::take(&mut *self.passed.lock().unwrap())
+    }
+}
+
+/// An in-memory backend that delivers whatever has been fed through its `ScriptedInput`.
+pub struct ScriptedBackend {
+    input: ScriptedInput,
+}
+
+impl ScriptedBackend {
+    pub fn new() -> Self {
+        Self {
+            input: ScriptedInput::default(),
+        }
+    }
+
+    pub fn get_input(&self) -> ScriptedInput {
+        self.input.clone()
+    }
+}
+
+impl Default for ScriptedBackend {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl InputBackend for ScriptedBackend {
+    fn install(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn poll(&mut self, on_input_key: &mut dyn FnMut(InputKey) -> InputDecision) -> io::Result<()> {
+        let mut delivered = false;
+        loop {
+            // don't hold the lock while calling back, the callback may feed more events
+            let input_key = self.input.queue.lock().unwrap().pop_front();
+            let input_key = match input_key {
+                Some(input_key) => input_key,
+                None => break,
+            };
+            match on_input_key(input_key) {
+                InputDecision::Pass => self.input.passed.lock().unwrap().push(input_key),
+                InputDecision::Consume => (),
+                InputDecision::Replace(input_keys) => self.replay(&input_keys)?,
+            }
+            delivered = true;
+        }
+        if !delivered {
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+        Ok(())
+    }
+
+    fn can_consume(&self) -> bool {
+        true
+    }
+
+    fn replay(&mut self, input_keys: &[InputKey]) -> io::Result<()> {
+        self.input
+            .passed
+            .lock()
+            .unwrap()
+            .extend_from_slice(input_keys);
+        Ok(())
+    }
+
+    fn now(&self) -> u32 {
+        self.input.now.load(Ordering::SeqCst)
+    }
+
+    fn uninstall(&mut self) {}
+}
@@ -0,0 +1,174 @@
+use super::{SimulateBackend, SimulatedEvent};
+use crate::virtual_key::{KeyCode, KeyOpt};
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::os::fd::AsRawFd;
+use std::os::unix::fs::OpenOptionsExt;
+
+const UINPUT_IOCTL_BASE: u32 = b'U' as u32;
+const UI_DEV_CREATE: libc::Ioctl = libc::_IO(UINPUT_IOCTL_BASE, 1);
+const UI_DEV_DESTROY: libc::Ioctl = libc::_IO(UINPUT_IOCTL_BASE, 2);
+const UI_DEV_SETUP: libc::Ioctl = libc::_IOW::<libc::uinput_setup>(UINPUT_IOCTL_BASE, 3);
+const UI_ABS_SETUP: libc::Ioctl = libc::_IOW::<libc::uinput_abs_setup>(UINPUT_IOCTL_BASE, 4);
+const UI_SET_EVBIT: libc::Ioctl = libc::_IOW::<libc::c_int>(UINPUT_IOCTL_BASE, 100);
+const UI_SET_KEYBIT: libc::Ioctl = libc::_IOW::<libc::c_int>(UINPUT_IOCTL_BASE, 101);
+const UI_SET_RELBIT: libc::Ioctl = libc::_IOW::<libc::c_int>(UINPUT_IOCTL_BASE, 102);
+const UI_SET_ABSBIT: libc::Ioctl = libc::_IOW::<libc::c_int>(UINPUT_IOCTL_BASE, 103);
+
+const EV_SYN: u16 = 0x00;
+const EV_KEY: u16 = 0x01;
+const EV_REL: u16 = 0x02;
+const EV_ABS: u16 = 0x03;
+const SYN_REPORT: u16 = 0x00;
+const REL_HWHEEL: u16 = 0x06;
+const REL_WHEEL: u16 = 0x08;
+const ABS_X: u16 = 0x00;
+const ABS_Y: u16 = 0x01;
+const BUS_VIRTUAL: u16 = 0x06;
+
+/// The name of the virtual device created by `UinputBackend`.
+pub const UINPUT_DEVICE_NAME: &str = "inbot virtual input";
+
+/// Sends synthetic input through a virtual device created with `/dev/uinput`.
+///
+/// The device has every key of `KeyCode` with an evdev code, a wheel, and absolute
+/// axes spanning the size given to `with_screen_size`, so that `MouseMove` positions
+/// map one to one onto a screen of that size. Creating it usually requires root or write access to `/dev/uinput`.
+///
+/// Either-side keys such as `KeyCode::Control` are sent as their left key. Sending a
+/// key without an evdev code fails with `io::ErrorKind::InvalidInput`.
+pub struct UinputBackend {
+    file: File,
+}
+
+impl UinputBackend {
+    pub fn new() -> io::Result<Self> {
+        Self::with_screen_size(1920, 1080)
+    }
+
+    pub fn with_screen_size(width: i32, height: i32) -> io::Result<Self> {
+        let file = OpenOptions::new()
+            .write(true)
+            .custom_flags(libc::O_NONBLOCK)
+            .open("/dev/uinput")?;
+        let fd = file.as_raw_fd();
+        unsafe {
+            for ev_type in [EV_SYN, EV_KEY, EV_REL, EV_ABS] {
+                ioctl_int(fd, UI_SET_EVBIT, ev_type as libc::c_int)?;
+            }
+            for key in KeyCode::known_keys()
+                .filter(|key| !key.is_either_side() && key.to_evdev_code() != 0)
+            {
+                ioctl_int(fd, UI_SET_KEYBIT, key.to_evdev_code() as libc::c_int)?;
+            }
+            for rel_code in [REL_WHEEL, REL_HWHEEL] {
+                ioctl_int(fd, UI_SET_RELBIT, rel_code as libc::c_int)?;
+            }
+            for (abs_code, maximum) in [(ABS_X, width - 1), (ABS_Y, height - 1)] {
+                ioctl_int(fd, UI_SET_ABSBIT, abs_code as libc::c_int)?;
+                let mut abs_setup: libc::uinput_abs_setup = std::mem::zeroed();
+                abs_setup.code = abs_code;
+                abs_setup.absinfo.maximum = maximum;
+                ioctl_ptr(fd, UI_ABS_SETUP, &abs_setup)?;
+            }
+            let mut setup: libc::uinput_setup = std::mem::zeroed();
+            setup.id.bustype = BUS_VIRTUAL;
+            for (dst, src) in setup.name.iter_mut().zip(UINPUT_DEVICE_NAME.bytes()) {
+                *dst = src as libc::c_char;
+            }
+            ioctl_ptr(fd, UI_DEV_SETUP, &setup)?;
+            ioctl_int(fd, UI_DEV_CREATE, 0)?;
+        }
+        Ok(Self { file })
+    }
+}
+
+impl Drop for UinputBackend {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = ioctl_int(self.file.as_raw_fd(), UI_DEV_DESTROY, 0);
+        }
+    }
+}
+
+impl SimulateBackend for UinputBackend {
+    fn send(&mut self, events: &[SimulatedEvent]) -> io::Result<()> {
+        let mut input_events = Vec::new();
+        for event in events {
+            match *event {
+                SimulatedEvent::Key { key, opt } if key.is_wheel() => {
+                    // a notch on down, the wheel has nothing to release
+                    if opt == KeyOpt::Up {
+                        continue;
+                    }
+                    let rel_event = match key {
+                        KeyCode::WheelUp => input_event(EV_REL, REL_WHEEL, 1),
+                        KeyCode::WheelDown => input_event(EV_REL, REL_WHEEL, -1),
+                        KeyCode::WheelRight => input_event(EV_REL, REL_HWHEEL, 1),
+                        _ => input_event(EV_REL, REL_HWHEEL, -1),
+                    };
+                    input_events.push(rel_event);
+                }
+                SimulatedEvent::Key { key, opt } => {
+                    let value = if opt == KeyOpt::Up { 0 } else { 1 };
+                    input_events.push(input_event(EV_KEY, get_evdev_code(key)?, value));
+                }
+                SimulatedEvent::MouseMove(pos) => {
+                    input_events.push(input_event(EV_ABS, ABS_X, pos.x));
+                    input_events.push(input_event(EV_ABS, ABS_Y, pos.y));
+                }
+                SimulatedEvent::Scroll { delta_x, delta_y } => {
+                    if delta_y != 0 {
+                        input_events.push(input_event(EV_REL, REL_WHEEL, delta_y));
+                    }
+                    if delta_x != 0 {
+                        input_events.push(input_event(EV_REL, REL_HWHEEL, delta_x));
+                    }
+                }
+            }
+            // one report per event, so that readers see them one at a time and in order
+            input_events.push(input_event(EV_SYN, SYN_REPORT, 0));
+        }
+        let bytes = unsafe {
+            std::slice::from_raw_parts(
+                input_events.as_ptr() as *const u8,
+                std::mem::size_of_val(input_events.as_slice()),
+            )
+        };
+        self.file.write_all(bytes)
+    }
+}
+
+/// keys the device doesn't have are an error rather than `KEY_RESERVED`
+fn get_evdev_code(key: KeyCode) -> io::Result<u16> {
+    match key.to_evdev_code() {
+        0 => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{:?} has no evdev code", key),
+        )),
+        code => Ok(code as u16),
+    }
+}
+
+fn input_event(type_: u16, code: u16, value: i32) -> libc::input_event {
+    // the kernel stamps the time itself
+    let mut event: libc::input_event = unsafe { std::mem::zeroed() };
+    event.type_ = type_;
+    event.code = code;
+    event.value = value;
+    event
+}
+
+unsafe fn ioctl_int(fd: libc::c_int, request: libc::Ioctl, value: libc::c_int) -> io::Result<()> {
+    if libc::ioctl(fd, request, value) < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+unsafe fn ioctl_ptr<T>(fd: libc::c_int, request: libc::Ioctl, value: &T) -> io::Result<()> {
+    if libc::ioctl(fd, request, value as *const T) < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
@@ -0,0 +1,405 @@
+use super::{InputBackend, InputDecision, SimulateBackend, SimulatedEvent};
+use crate::diagnostics::diag;
+use crate::virtual_key::{InputKey, KeyCode, KeyOpt, WHEEL_NOTCH};
+use std::cell::Cell;
+use std::io;
+use windows::Win32::Foundation::{HMODULE, LPARAM, LRESULT, WPARAM};
+use windows::Win32::System::SystemInformation::GetTickCount;
+use windows::Win32::UI::Input::KeyboardAndMouse::{
+    MapVirtualKeyW, SendInput, INPUT, INPUT_0, INPUT_KEYBOARD, INPUT_MOUSE, KEYBDINPUT,
+    KEYBD_EVENT_FLAGS, KEYEVENTF_EXTENDEDKEY, KEYEVENTF_KEYUP, KEYEVENTF_SCANCODE,
+    MAPVK_VK_TO_VSC_EX, MOUSEEVENTF_HWHEEL, MOUSEEVENTF_LEFTDOWN, MOUSEEVENTF_LEFTUP,
+    MOUSEEVENTF_MIDDLEDOWN, MOUSEEVENTF_MIDDLEUP, MOUSEEVENTF_RIGHTDOWN, MOUSEEVENTF_RIGHTUP,
+    MOUSEEVENTF_WHEEL, MOUSEEVENTF_XDOWN, MOUSEEVENTF_XUP, MOUSEINPUT, MOUSE_EVENT_FLAGS,
+    VIRTUAL_KEY,
+};
+use windows::Win32::UI::WindowsAndMessaging::{
+    CallNextHookEx, PeekMessageW, SetCursorPos, SetWindowsHookExW, UnhookWindowsHookEx, HC_ACTION,
+    HHOOK, KBDLLHOOKSTRUCT, MSG, MSLLHOOKSTRUCT, PM_REMOVE, WHEEL_DELTA, WH_KEYBOARD_LL,
+    WH_MOUSE_LL, XBUTTON1, XBUTTON2,
+};
+
+type InputKeySink = *mut (dyn FnMut(InputKey) -> InputDecision + 'static);
+
+/// `dwExtraInfo` of replayed events, so that the hooks let them through untouched
+const REPLAY_EXTRA_INFO: usize = 0x696e_6274;
+
+thread_local! {
+    // Low-level hooks only run while the installing thread is pumping messages,
+    // so the sink is only set for the duration of `poll`.
+    static INPUT_KEY_SINK: Cell<Option<InputKeySink>> = const { Cell::new(None) };
+}
+
+/// Listens through the `WH_KEYBOARD_LL` and `WH_MOUSE_LL` low-level hooks.
+pub struct Win32Backend {
+    keyboard_hook: HHOOK,
+    mouse_hook: HHOOK,
+}
+
+// The hooks are installed, pumped and removed on the listener thread only.
+unsafe impl Send for Win32Backend {}
+
+impl Win32Backend {
+    pub fn new() -> Self {
+        Self {
+            keyboard_hook: HHOOK::default(),
+            mouse_hook: HHOOK::default(),
+        }
+    }
+}
+
+impl Default for Win32Backend {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl InputBackend for Win32Backend {
+    fn install(&mut self) -> io::Result<()> {
+        unsafe {
+            self.keyboard_hook = SetWindowsHookExW(
+                WH_KEYBOARD_LL,
+                Some(keyboard_callback),
+                HMODULE::default(),
+                0,
+            )
+            .map_err(io::Error::other)?;
+            match SetWindowsHookExW(WH_MOUSE_LL, Some(mouse_callback), HMODULE::default(), 0) {
+                Ok(mouse_hook) => self.mouse_hook = mouse_hook,
+                Err(e) => {
+                    // don't leave the keyboard hook behind, `uninstall` isn't called on failure
+                    let _ = UnhookWindowsHookEx(self.keyboard_hook);
+                    self.keyboard_hook = HHOOK::default();
+                    return Err(io::Error::other(e));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn poll(&mut self, on_input_key: &mut dyn FnMut(InputKey) -> InputDecision) -> io::Result<()> {
+        let sink: *mut (dyn FnMut(InputKey) -> InputDecision + '_) = on_input_key;
+        let sink: InputKeySink = unsafe { std::mem::transmute(sink) };
+        INPUT_KEY_SINK.with(|cell| cell.set(Some(sink)));
+        let mut msg = MSG::default();
+        let result = unsafe { PeekMessageW(&mut msg, None, 0, 0, PM_REMOVE).0 };
+        INPUT_KEY_SINK.with(|cell| cell.set(None));
+        match result {
+            -1 => return Err(io::Error::other("get message failed")),
+            0 => std::thread::sleep(std::time::Duration::from_millis(1)),
+            _ => (),
+        }
+        Ok(())
+    }
+
+    fn can_consume(&self) -> bool {
+        true
+    }
+
+    fn replay(&mut self, input_keys: &[InputKey]) -> io::Result<()> {
+        send_replay_inputs(input_keys)
+    }
+
+    fn now(&self) -> u32 {
+        // hook events are stamped with the tick count
+        unsafe { GetTickCount() }
+    }
+
+    fn uninstall(&mut self) {
+        unsafe {
+            let _ = UnhookWindowsHookEx(self.keyboard_hook);
+            let _ = UnhookWindowsHookEx(self.mouse_hook);
+        }
+        self.keyboard_hook = HHOOK::default();
+        self.mouse_hook = HHOOK::default();
+    }
+}
+
+extern "system" fn keyboard_callback(ncode: i32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+    unsafe {
+        let extra_info = (*(lparam.0 as *const KBDLLHOOKSTRUCT)).dwExtraInfo;
+        device_event_callback(ncode, wparam, lparam, extra_info)
+    }
+}
+
+extern "system" fn mouse_callback(ncode: i32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+    unsafe {
+        let extra_info = (*(lparam.0 as *const MSLLHOOKSTRUCT)).dwExtraInfo;
+        device_event_callback(ncode, wparam, lparam, extra_info)
+    }
+}
+
+unsafe fn device_event_callback(
+    ncode: i32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+    extra_info: usize,
+) -> LRESULT {
+    let mut decision = InputDecision::Pass;
+    if (ncode == HC_ACTION as i32) && (extra_info != REPLAY_EXTRA_INFO) {
+        if let Some(input_key) = InputKey::from(wparam, lparam) {
+            if let Some(sink) = INPUT_KEY_SINK.with(|cell| cell.get()) {
+                decision = (*sink)(input_key);
+                if input_key.key.is_wheel() {
+                    // the wheel has no release, so a notch is reported as a tap
+                    let up_key = InputKey {
+                        opt: KeyOpt::Up,
+                        ..input_key
+                    };
+                    let up_decision = (*sink)(up_key);
+                    decision = merge_decisions(input_key, decision, up_key, up_decision);
+                }
+            }
+        } else {
+            diag!(debug, "unknown input message:{:#x}", wparam.0);
+        }
+    }
+    match decision {
+        InputDecision::Pass => (),
+        InputDecision::Consume => return LRESULT(1),
+        InputDecision::Replace(input_keys) => {
+            // injected events are queued behind this one, which is swallowed to keep the order
+            if let Err(e) = send_replay_inputs(&input_keys) {
+                diag!(warn, "replay input keys failed, {}", e);
+            }
+            return LRESULT(1);
+        }
+    }
+    // `hhk` is ignored by the system.
+    CallNextHookEx(HHOOK::default(), ncode, wparam, lparam)
+}
+
+/// the decision for both halves of a wheel tap, which share one hook event
+fn merge_decisions(
+    down_key: InputKey,
+    down_decision: InputDecision,
+    up_key: InputKey,
+    up_decision: InputDecision,
+) -> InputDecision {
+    if down_decision == InputDecision::Pass && up_decision == InputDecision::Pass {
+        return InputDecision::Pass;
+    }
+    let mut input_keys = Vec::new();
+    for (input_key, decision) in [(down_key, down_decision), (up_key, up_decision)] {
+        match decision {
+            InputDecision::Pass => input_keys.push(input_key),
+            InputDecision::Consume => (),
+            InputDecision::Replace(mut replace_keys) => input_keys.append(&mut replace_keys),
+        }
+    }
+    if input_keys.is_empty() {
+        return InputDecision::Consume;
+    }
+    InputDecision::Replace(input_keys)
+}
+
+fn send_replay_inputs(input_keys: &[InputKey]) -> io::Result<()> {
+    let inputs: Vec<INPUT> = input_keys
+        .iter()
+        .filter_map(|input_key| {
+            key_input(
+                input_key.key,
+                input_key.opt,
+                input_key.scan_code,
+                input_key.wheel_delta,
+                REPLAY_EXTRA_INFO,
+            )
+        })
+        .collect();
+    send_inputs(&inputs)
+}
+
+/// Sends synthetic input through `SendInput`.
+pub struct Win32SimulateBackend;
+
+impl Win32SimulateBackend {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Default for Win32SimulateBackend {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SimulateBackend for Win32SimulateBackend {
+    fn send(&mut self, events: &[SimulatedEvent]) -> io::Result<()> {
+        let mut inputs = Vec::with_capacity(events.len());
+        for event in events {
+            match *event {
+                SimulatedEvent::Key { key, opt } => inputs.extend(key_input(key, opt, 0, 0, 0)),
+                SimulatedEvent::MouseMove(pos) => {
+                    // flush what's queued so far to keep the order
+                    send_inputs(&inputs)?;
+                    inputs.clear();
+                    unsafe { SetCursorPos(pos.x, pos.y) }.map_err(io::Error::other)?;
+                }
+                SimulatedEvent::Scroll { delta_x, delta_y } => {
+                    if delta_y != 0 {
+                        inputs.push(mouse_input(
+                            MOUSEEVENTF_WHEEL,
+                            delta_y * WHEEL_DELTA as i32,
+                            0,
+                        ));
+                    }
+                    if delta_x != 0 {
+                        inputs.push(mouse_input(
+                            MOUSEEVENTF_HWHEEL,
+                            delta_x * WHEEL_DELTA as i32,
+                            0,
+                        ));
+                    }
+                }
+            }
+        }
+        send_inputs(&inputs)
+    }
+}
+
+fn send_inputs(inputs: &[INPUT]) -> io::Result<()> {
+    if inputs.is_empty() {
+        return Ok(());
+    }
+    let sent = unsafe { SendInput(inputs, std::mem::size_of::<INPUT>() as i32) };
+    if sent as usize != inputs.len() {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// `None` for the `Up` of wheel keys, which have nothing to release;
+/// a `wheel_delta` of 0 scrolls one notch
+///
+/// A key with a `scan_code`, see `InputKey::scan_code`, is sent by the scan code like the
+/// original event. Otherwise it is sent by its virtual key, along with the scan code the
+/// current layout gives that key.
+fn key_input(
+    key: KeyCode,
+    opt: KeyOpt,
+    scan_code: u32,
+    wheel_delta: i32,
+    extra_info: usize,
+) -> Option<INPUT> {
+    let is_up = opt == KeyOpt::Up;
+    if key.is_wheel() {
+        if is_up {
+            return None;
+        }
+        let wheel_delta = if wheel_delta == 0 {
+            WHEEL_NOTCH
+        } else {
+            wheel_delta
+        };
+        let input = match key {
+            KeyCode::WheelUp => mouse_input(MOUSEEVENTF_WHEEL, wheel_delta, extra_info),
+            KeyCode::WheelDown => mouse_input(MOUSEEVENTF_WHEEL, -wheel_delta, extra_info),
+            KeyCode::WheelRight => mouse_input(MOUSEEVENTF_HWHEEL, wheel_delta, extra_info),
+            _ => mouse_input(MOUSEEVENTF_HWHEEL, -wheel_delta, extra_info),
+        };
+        return Some(input);
+    }
+    let mouse_flags = match key {
+        KeyCode::MouseLeft => Some((MOUSEEVENTF_LEFTDOWN, MOUSEEVENTF_LEFTUP, 0)),
+        KeyCode::MouseRight => Some((MOUSEEVENTF_RIGHTDOWN, MOUSEEVENTF_RIGHTUP, 0)),
+        KeyCode::MouseMiddle => Some((MOUSEEVENTF_MIDDLEDOWN, MOUSEEVENTF_MIDDLEUP, 0)),
+        // `mouseData` of `MOUSEEVENTF_XDOWN` and `MOUSEEVENTF_XUP`
+        KeyCode::MouseX1 => Some((MOUSEEVENTF_XDOWN, MOUSEEVENTF_XUP, XBUTTON1 as i32)),
+        KeyCode::MouseX2 => Some((MOUSEEVENTF_XDOWN, MOUSEEVENTF_XUP, XBUTTON2 as i32)),
+        _ => None,
+    };
+    if let Some((down_flags, up_flags, mouse_data)) = mouse_flags {
+        let flags = if is_up { up_flags } else { down_flags };
+        return Some(mouse_input(flags, mouse_data, extra_info));
+    }
+    let mut flags = KEYBD_EVENT_FLAGS::default();
+    if is_up {
+        flags = flags | KEYEVENTF_KEYUP;
+    }
+    let is_extended = if scan_code != 0 {
+        flags = flags | KEYEVENTF_SCANCODE;
+        scan_code & 0xFF00 == 0xE000
+    } else {
+        is_extended_key(key)
+    };
+    if is_extended {
+        flags = flags | KEYEVENTF_EXTENDEDKEY;
+    }
+    let scan_code = match scan_code {
+        0 => unsafe { MapVirtualKeyW(key.to_windows_id(), MAPVK_VK_TO_VSC_EX) },
+        scan_code => scan_code,
+    };
+    Some(INPUT {
+        r#type: INPUT_KEYBOARD,
+        Anonymous: INPUT_0 {
+            ki: KEYBDINPUT {
+                wVk: VIRTUAL_KEY(key.to_windows_id() as u16),
+                wScan: (scan_code & 0xFF) as u16,
+                dwFlags: flags,
+                time: 0,
+                dwExtraInfo: extra_info,
+            },
+        },
+    })
+}
+
+fn mouse_input(flags: MOUSE_EVENT_FLAGS, mouse_data: i32, extra_info: usize) -> INPUT {
+    INPUT {
+        r#type: INPUT_MOUSE,
+        Anonymous: INPUT_0 {
+            mi: MOUSEINPUT {
+                dx: 0,
+                dy: 0,
+                mouseData: mouse_data as u32,
+                dwFlags: flags,
+                time: 0,
+                dwExtraInfo: extra_info,
+            },
+        },
+    }
+}
+
+/// keys that a real keyboard reports with the extended-key flag
+fn is_extended_key(key: KeyCode) -> bool {
+    matches!(
+        key,
+        KeyCode::ControlRight
+            | KeyCode::AltRight
+            | KeyCode::MetaLeft
+            | KeyCode::MetaRight
+            | KeyCode::Insert
+            | KeyCode::Delete
+            | KeyCode::Home
+            | KeyCode::End
+            | KeyCode::PageUp
+            | KeyCode::PageDown
+            | KeyCode::UpArrow
+            | KeyCode::DownArrow
+            | KeyCode::LeftArrow
+            | KeyCode::RightArrow
+            | KeyCode::Printscreen
+            | KeyCode::NumpadEnter
+            | KeyCode::NumpadDivide
+            | KeyCode::NumLock
+            | KeyCode::ContextMenu
+            | KeyCode::VolumeMute
+            | KeyCode::VolumeDown
+            | KeyCode::VolumeUp
+            | KeyCode::MediaNextTrack
+            | KeyCode::MediaPrevTrack
+            | KeyCode::MediaStop
+            | KeyCode::MediaPlayPause
+            | KeyCode::LaunchMail
+            | KeyCode::LaunchMediaSelect
+            | KeyCode::LaunchApp1
+            | KeyCode::LaunchApp2
+            | KeyCode::BrowserBack
+            | KeyCode::BrowserForward
+            | KeyCode::BrowserRefresh
+            | KeyCode::BrowserStop
+            | KeyCode::BrowserSearch
+            | KeyCode::BrowserFavorites
+            | KeyCode::BrowserHome
+    )
+}
@@ -1,92 +1,363 @@
+use crate::backend::InputDecision;
+use crate::diagnostics::{diag, enter_span};
+use crate::error::Error;
 use crate::virtual_key::*;
 use std::sync::mpsc::Sender;
+use std::sync::Arc;
+#[derive(PartialEq, Eq, Clone, Debug)]
 pub struct BindingKey {
     pub key: KeyCode,
     pub modifer_keys: Vec<KeyCode>,
 }
-use std::cell::RefCell;
 use std::collections::{HashMap, HashSet};
-use std::hash::{Hash, Hasher};
-use std::rc::Rc;
-use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::time::Duration;
+
+/// 默认的按键序列超时时间
+pub const DEFAULT_SEQUENCE_TIMEOUT: Duration = Duration::from_millis(1000);
+/// 默认的歧义前缀等待时间，见`ListenerProxy::set_ambiguous_timeout`
+pub const DEFAULT_AMBIGUOUS_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// `Trigger::Hold`重复触发的最短间隔
+const MIN_HOLD_REPEAT: Duration = Duration::from_millis(1);
+
+/// 绑定的匹配结果，通过`BindingInfo::notifier`发送
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub(crate) enum BindingNotice {
+    /// 触发绑定
+    Triggered(u32),
+    /// 绑定已经完全匹配，但也是更长绑定的前缀，等待下一个按键
+    Pending(u32),
+    /// 等待中的绑定被更长的绑定取代，不会触发
+    Cancelled(u32),
+    /// 等待中的绑定不再等待下一个按键，之后按自己的`Trigger`触发
+    Resolved(u32),
+    /// 绑定和已有的绑定冲突，没有生效
+    Rejected(u32),
+    /// 绑定已经移除，不会再触发
+    Unbound(u32),
+}
+
+/// 按下绑定按键时，如何看待绑定之外还被按住的按键
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default)]
+pub enum MatchPolicy {
+    /// 除了绑定的修饰键，不能按住任何其它按键
+    #[default]
+    Exact,
+    /// 绑定的修饰键都按住即可，允许按住其它任意按键
+    Subset,
+    /// 按住的修饰键必须和绑定的一致，忽略按住的非修饰键，例如鼠标按键
+    IgnoreNonModifiers,
+}
+
+/// 绑定的按键对应的是逻辑按键还是物理按键，见`InputKey::physical_key`
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default)]
+pub enum KeyIdentity {
+    /// 按键布局决定的按键，例如AZERTY布局下，按下美式键盘上Q的位置得到A
+    #[default]
+    Logical,
+    /// 美式键盘上同样位置的按键，不受按键布局影响，例如游戏中的WASD
+    Physical,
+}
+
+/// 绑定在最后一个按键的什么时候触发
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default)]
+pub enum Trigger {
+    /// 按下时触发，按住时随着按键的自动重复再次触发
+    #[default]
+    Press,
+    /// 松开时触发
+    Release,
+    /// 按住超过`duration`后触发，如果`repeat`不为`None`，之后在松开前每隔`repeat`再次触发
+    Hold {
+        duration: Duration,
+        repeat: Option<Duration>,
+    },
+    /// 连续点按`count`次后触发，相邻两次点按的间隔和每次按住的时间都不能超过`interval`
+    /// 同样的按键还绑定了更多次点按时，等待`interval`确认没有下一次点按后才触发，
+    /// 例如双击Shift不会同时触发单击Shift
+    MultiTap { count: u32, interval: Duration },
+}
+
+/// 默认的连续点按间隔，见`Trigger::MultiTap`
+pub const DEFAULT_TAP_INTERVAL: Duration = Duration::from_millis(300);
+
+impl Trigger {
+    /// 使用默认点按间隔的`Trigger::MultiTap`
+    pub fn multi_tap(count: u32) -> Self {
+        Trigger::MultiTap {
+            count,
+            interval: DEFAULT_TAP_INTERVAL,
+        }
+    }
+}
+
+/// 默认的点按时间，见`DualRole::tapping_term`
+pub const DEFAULT_TAPPING_TERM: Duration = Duration::from_millis(200);
+
+/// 双重功能按键，例如CapsLock点按时作为Esc，按住时作为Ctrl
+///
+/// 绑定必须是单个没有修饰键的按键。按下该按键后到松开前没有按下其它按键，
+/// 并且按住的时间短于`tapping_term`时，视为点按，触发绑定。
+/// 按住时该按键视为`hold_as`参与其它绑定的匹配，例如(CapsLock + C)可以匹配(Ctrl + C)
+///
+/// `hold_as`只影响inbot的绑定，其它程序看到的仍然是该按键，不会看到`hold_as`，
+/// 例如CapsLock仍然切换大写锁定。设置`BindingOptions::consume`时该按键不再发送给其它程序
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct DualRole {
+    pub hold_as: KeyCode,
+    pub tapping_term: Duration,
+}
+
+impl DualRole {
+    pub fn new(hold_as: KeyCode) -> Self {
+        Self {
+            hold_as,
+            tapping_term: DEFAULT_TAPPING_TERM,
+        }
+    }
+}
+
+/// 绑定的可选项
+#[derive(Clone, Debug, Default)]
+pub struct BindingOptions {
+    pub match_policy: MatchPolicy,
+    pub trigger: Trigger,
+    /// 按键序列中相邻两个按键之间允许的最长间隔，超时后需要从第一个按键重新匹配
+    /// 为`None`时使用全局默认值，见`ListenerProxy::set_default_sequence_timeout`
+    pub sequence_timeout: Option<Duration>,
+    /// 不为`None`时绑定是双重功能按键，`trigger`不起作用
+    pub dual_role: Option<DualRole>,
+    /// 拦截匹配的按键，不再发送给其它程序，见`BindingKeyMgr::on_input_key`
+    /// 只有能拦截输入的后端支持，见`InputBackend::can_consume`
+    pub consume: bool,
+    /// 绑定的按键和修饰键按逻辑按键还是物理按键匹配
+    pub key_identity: KeyIdentity,
+    /// 绑定所属的分组，可以整组启用或者禁用，见`ListenerProxy::disable_group`
+    pub group: Option<String>,
+    /// 绑定所属的按键表，只在按键表压入栈中时参与匹配，见`ListenerProxy::push_keymap`
+    /// 为`None`时属于最底层，始终参与匹配
+    pub keymap: Option<String>,
+}
 
 pub struct BindingInfo {
     binding_uid: u32,
-    notifier: Sender<u32>,
+    notifier: Sender<BindingNotice>,
+    active: Arc<AtomicBool>, // shared with the `BindingHandle`, cleared once unbound or rejected
+    enabled: bool,
     keys: Vec<BindingKey>,
-    matching_index: usize, // index of `Self::keys`. when input key matched, the value of `matching_index` + 1
+    options: BindingOptions,
+}
+
+/// 按下或者松开的按键，同时记录逻辑按键和物理按键，见`KeyIdentity`
+#[derive(Clone, Copy)]
+struct PressedKey {
+    key: KeyCode,
+    physical_key: KeyCode,
+}
+
+impl PressedKey {
+    fn from(input_key: &InputKey) -> Self {
+        Self {
+            key: input_key.key,
+            physical_key: input_key.physical_key,
+        }
+    }
+
+    fn get(&self, key_identity: KeyIdentity) -> KeyCode {
+        match key_identity {
+            KeyIdentity::Logical => self.key,
+            KeyIdentity::Physical => self.physical_key,
+        }
+    }
+}
+
+/// 一个绑定的部分匹配，同一个绑定可以同时有多个部分匹配，例如(A, A, B)输入A, A时
+struct PartialMatch {
+    binding_uid: u32,
+    next_index: usize,    // index of `BindingInfo::keys` to match next
+    last_match_time: u32, // time of the last matched input key, see `InputKey::time`
+}
+
+/// 已经完全匹配、等待松开或者按住足够时间才触发的绑定，见`Trigger`
+struct HeldMatch {
+    binding_uid: u32,
+    key: KeyCode, // the pressed key, never an either-side key
+    down_time: u32,
+    triggered_count: u32,
+    interrupted: bool, // other keys were pressed while held, see `Trigger::MultiTap`
+    released_time: Option<u32>, // released while the binding waits for the next key
+}
+
+/// `Trigger::MultiTap`绑定的连续点按次数
+#[derive(Default)]
+struct TapCount {
+    count: u32,
+    last_tap_time: u32,
+    deferred: bool, // reached `count` but waits for more taps of a binding with a larger count
+}
+
+/// 按下的双重功能按键
+struct DualRoleDown {
+    binding_uid: u32,
+    hold_as: KeyCode,
+    down_time: u32,
+    interrupted: bool, // other keys were pressed while held
 }
-type BindingInfoMutRc = Rc<RefCell<BindingInfo>>;
 
-enum MatchKeyResult {
-    Failed,            // 不匹配
-    Matching(KeyCode), // 匹配成功，后续还有按键需要继续匹配
-    Success,           // 完全匹配
+/// 当前按下的按键和拦截按键的绑定的匹配情况
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default)]
+enum Consumption {
+    #[default]
+    None,
+    Partial, // advanced a partial match
+    Matched, // fully matched
+}
+
+/// 等待下一个按键的完全匹配
+struct PendingMatch {
+    binding_uids: Vec<u32>,
+    blocking_matches: Vec<(u32, usize)>, // (binding_uid, next_index) of longer partial matches
+    time: u32,
 }
 
 impl BindingInfo {
-    pub fn new(binding_keys: Vec<BindingKey>, notifier: Sender<u32>) -> Self {
+    pub fn new(
+        binding_keys: Vec<BindingKey>,
+        options: BindingOptions,
+        notifier: Sender<BindingNotice>,
+    ) -> Self {
         static LAST_ALLOCATED_SUBSCRIPTION_UID: AtomicU32 = AtomicU32::new(0);
         let uid = LAST_ALLOCATED_SUBSCRIPTION_UID.fetch_add(1, Ordering::SeqCst) + 1;
         Self {
             binding_uid: uid,
             notifier,
+            active: Arc::new(AtomicBool::new(true)),
+            enabled: true,
             keys: binding_keys,
-            matching_index: 0,
+            options,
         }
     }
 
-    fn try_match(&mut self, input_key: KeyCode, holding_keys: &HashSet<KeyCode>) -> MatchKeyResult {
-        if !self.if_key_matched(input_key, holding_keys) {
-            self.matching_index = 0;
-            return MatchKeyResult::Failed;
+    /// 检查绑定是否可能匹配，`ListenerProxy`发送绑定之前就检查，以便同步返回错误
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.keys.is_empty() {
+            return Err(Error::InvalidBinding("the binding has no keys".to_string()));
         }
-        self.matching_index += 1;
-        if let Some(to_match_key) = self.keys.get(self.matching_index) {
-            return MatchKeyResult::Matching(to_match_key.key);
+        if self.options.dual_role.is_some()
+            && (self.keys.len() != 1 || !self.keys[0].modifer_keys.is_empty())
+        {
+            return Err(Error::InvalidBinding(
+                "a dual-role binding must be a single key without modifers".to_string(),
+            ));
         }
-        self.matching_index = 0;
-        let _ = self.notifier.send(self.binding_uid);
-        return MatchKeyResult::Success;
+        if let Trigger::MultiTap { count: 0, .. } = self.options.trigger {
+            return Err(Error::InvalidBinding(
+                "a multi-tap trigger needs a count of at least 1".to_string(),
+            ));
+        }
+        Ok(())
     }
 
-    fn if_key_matched(&self, key: KeyCode, holding_keys: &HashSet<KeyCode>) -> bool {
-        let matching_key = self.keys.get(self.matching_index);
+    fn if_key_matched(
+        &self,
+        index: usize,
+        pressed_key: PressedKey,
+        holding_keys: &HashSet<KeyCode>,
+        holding_physical_keys: &HashSet<KeyCode>,
+    ) -> bool {
+        let key = pressed_key.get(self.options.key_identity);
+        let holding_keys = match self.options.key_identity {
+            KeyIdentity::Logical => holding_keys,
+            KeyIdentity::Physical => holding_physical_keys,
+        };
+        let matching_key = self.keys.get(index);
         if matching_key.is_none() {
             return false;
         }
         let matching_key = matching_key.unwrap();
-        if matching_key.key != key {
+        if !matching_key.key.matches(key) {
             return false;
         }
         for must_holding_key in &matching_key.modifer_keys {
-            if !holding_keys.contains(&must_holding_key) {
+            // 左右不限的修饰键，按下任意一侧都可以
+            if !holding_keys
+                .iter()
+                .any(|holding_key| must_holding_key.matches(*holding_key))
+            {
                 return false;
             }
         }
-        if matching_key.modifer_keys.len() + 1 != holding_keys.len() {
-            return false;
-        }
-        return true;
+        let other_holding_keys = holding_keys
+            .iter()
+            .filter(|holding_key| **holding_key != key);
+        let other_holding_count = match self.options.match_policy {
+            MatchPolicy::Subset => return true,
+            MatchPolicy::Exact => other_holding_keys.count(),
+            MatchPolicy::IgnoreNonModifiers => other_holding_keys
+                .filter(|holding_key| holding_key.is_modifier())
+                .count(),
+        };
+        matching_key.modifer_keys.len() == other_holding_count
+    }
+
+    /// 距离部分匹配上次匹配的按键已经超时
+    fn is_sequence_expired(
+        &self,
+        partial_match: &PartialMatch,
+        time: u32,
+        default_timeout: Option<Duration>,
+    ) -> bool {
+        let timeout = match self.options.sequence_timeout.or(default_timeout) {
+            Some(timeout) => timeout,
+            None => return false,
+        };
+        let elapsed = time.wrapping_sub(partial_match.last_match_time);
+        Duration::from_millis(elapsed as u64) > timeout
     }
 
     pub fn get_first_key(&self) -> Option<KeyCode> {
         if self.keys.is_empty() {
             return None;
         }
-        return Some(self.keys.first().unwrap().key);
+        Some(self.keys.first().unwrap().key)
     }
 
     pub fn get_uid(&self) -> u32 {
         self.binding_uid
     }
+
+    /// 绑定是否仍然有效，移除或者被拒绝后为`false`
+    pub fn get_active_flag(&self) -> Arc<AtomicBool> {
+        self.active.clone()
+    }
+
+    fn notify(&self, notice: BindingNotice) {
+        let _span = enter_span!("binding", uid = self.binding_uid);
+        diag!(trace, "{:?}", notice);
+        let _ = self.notifier.send(notice);
+    }
 }
 
 pub struct BindingKeyMgr {
-    bindings_info: HashMap<u32, BindingInfoMutRc>,
-    first_key_to_match: HashMap<KeyCode, HashMap<u32, BindingInfoMutRc>>,
-    to_match_keys: HashMap<KeyCode, HashMap<u32, BindingInfoMutRc>>,
+    bindings_info: HashMap<u32, BindingInfo>,
+    first_key_to_match: HashMap<KeyCode, HashSet<u32>>,
+    partial_matches: Vec<PartialMatch>,
+    pending_match: Option<PendingMatch>,
+    held_matches: Vec<HeldMatch>,
+    tap_counts: HashMap<u32, TapCount>,
+    dual_role_keys: HashMap<KeyCode, u32>,
+    dual_role_downs: HashMap<KeyCode, DualRoleDown>,
     holding_keys: HashSet<KeyCode>,
+    holding_physical_keys: HashSet<KeyCode>,
+    consumption: Consumption,
+    consumed_keys: Vec<InputKey>, // consumed keys of sequences that may still fail
+    consumed_downs: HashSet<KeyCode>,
+    replay_keys: Vec<InputKey>,
+    default_sequence_timeout: Option<Duration>,
+    ambiguous_timeout: Duration,
+    paused: bool,
+    keymaps: Vec<String>, // the keymap stack, the last one on top
 }
 
 impl BindingKeyMgr {
@@ -94,109 +365,917 @@ impl BindingKeyMgr {
         Self {
             bindings_info: HashMap::new(),
             first_key_to_match: HashMap::new(),
-            to_match_keys: HashMap::new(),
+            partial_matches: Vec::new(),
+            pending_match: None,
+            held_matches: Vec::new(),
+            tap_counts: HashMap::new(),
+            dual_role_keys: HashMap::new(),
+            dual_role_downs: HashMap::new(),
             holding_keys: HashSet::new(),
+            holding_physical_keys: HashSet::new(),
+            consumption: Consumption::None,
+            consumed_keys: Vec::new(),
+            consumed_downs: HashSet::new(),
+            replay_keys: Vec::new(),
+            default_sequence_timeout: Some(DEFAULT_SEQUENCE_TIMEOUT),
+            ambiguous_timeout: DEFAULT_AMBIGUOUS_TIMEOUT,
+            paused: false,
+            keymaps: Vec::new(),
         }
     }
 }
 
 impl BindingKeyMgr {
-    pub fn bind(&mut self, binding_info: BindingInfo) {
+    /// 绑定失败时同时通过`BindingNotice::Rejected`通知绑定所属的`ListenerProxy`
+    pub fn bind(&mut self, binding_info: BindingInfo) -> Result<(), Error> {
+        let uid = binding_info.binding_uid;
+        let result = if self.bindings_info.contains_key(&uid) {
+            Err(Error::DuplicateBinding(uid))
+        } else {
+            binding_info.validate()
+        };
+        if let Err(e) = result {
+            diag!(warn, "bind uid:{} failed, {}", uid, e);
+            binding_info.active.store(false, Ordering::SeqCst);
+            binding_info.notify(BindingNotice::Rejected(uid));
+            return Err(e);
+        }
+        if binding_info.options.dual_role.is_some() {
+            return self.bind_dual_role(binding_info);
+        }
+        for first_key in get_physical_keys(binding_info.get_first_key().unwrap()) {
+            let bindings_of_keys = self.first_key_to_match.entry(first_key).or_default();
+            bindings_of_keys.insert(uid);
+        }
+        self.bindings_info.insert(uid, binding_info);
+        Ok(())
+    }
+
+    /// 双重功能按键不参与普通的匹配，见`Self::on_input_key`
+    /// 同一个按键只能有一个双重功能按键的绑定
+    fn bind_dual_role(&mut self, binding_info: BindingInfo) -> Result<(), Error> {
         let uid = binding_info.binding_uid;
-        if self.bindings_info.contains_key(&uid) {
-            println!("subscription uid:{} already exist", uid);
+        let binding_key = &binding_info.keys[0];
+        let is_bound = get_physical_keys(binding_key.key)
+            .iter()
+            .any(|key| self.dual_role_keys.contains_key(key));
+        if is_bound {
+            diag!(
+                warn,
+                "key:{:?} is already bound as a dual-role key",
+                binding_key.key
+            );
+            binding_info.active.store(false, Ordering::SeqCst);
+            binding_info.notify(BindingNotice::Rejected(uid));
+            return Err(Error::DuplicateBinding(uid));
+        }
+        for key in get_physical_keys(binding_key.key) {
+            self.dual_role_keys.insert(key, uid);
+        }
+        self.bindings_info.insert(uid, binding_info);
+        Ok(())
+    }
+
+    pub fn unbind(&mut self, uid: u32) {
+        if !self.bindings_info.contains_key(&uid) {
             return;
         }
-        let first_key = binding_info.get_first_key();
-        if first_key.is_none() {
+        self.clear_match_state(&HashSet::from([uid]));
+        let binding_info = self.bindings_info.remove(&uid).unwrap();
+        for first_key in get_physical_keys(binding_info.get_first_key().unwrap()) {
+            if let Some(bindings_of_key) = self.first_key_to_match.get_mut(&first_key) {
+                bindings_of_key.remove(&uid);
+                if bindings_of_key.is_empty() {
+                    self.first_key_to_match.remove(&first_key);
+                }
+            }
+        }
+        self.dual_role_keys
+            .retain(|_, binding_uid| *binding_uid != uid);
+        binding_info.active.store(false, Ordering::SeqCst);
+        binding_info.notify(BindingNotice::Unbound(uid));
+    }
+
+    /// 禁用的绑定不参与匹配，直到重新启用，禁用时清除绑定的匹配状态，见`Self::clear_match_state`
+    pub fn set_enabled(&mut self, uid: u32, enabled: bool) {
+        let binding_info = match self.bindings_info.get_mut(&uid) {
+            Some(binding_info) => binding_info,
+            None => return,
+        };
+        binding_info.enabled = enabled;
+        if !enabled {
+            self.clear_match_state(&HashSet::from([uid]));
+        }
+    }
+
+    /// 启用或者禁用`BindingOptions::group`为`group`的所有绑定
+    pub fn set_group_enabled(&mut self, group: &str, enabled: bool) {
+        let mut uids = HashSet::new();
+        for binding_info in self.bindings_info.values_mut() {
+            if binding_info.options.group.as_deref() == Some(group) {
+                binding_info.enabled = enabled;
+                uids.insert(binding_info.binding_uid);
+            }
+        }
+        if !enabled {
+            self.clear_match_state(&uids);
+        }
+    }
+
+    /// 暂停时所有绑定都不参与匹配，但保留各自的启用状态，恢复后照旧
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+        if paused {
+            let uids: HashSet<u32> = self.bindings_info.keys().copied().collect();
+            self.clear_match_state(&uids);
+        }
+    }
+
+    /// 按键表压入栈顶，其中的绑定开始参与匹配，并且遮蔽下层同样按键的绑定，
+    /// 见`Self::update_partial_matches`
+    ///
+    /// 下层绑定进行中的部分匹配，如果下一个按键是按键表中绑定的首个按键，也被遮蔽而放弃，
+    /// 被它阻塞的等待中的绑定照常在下一个按键或者超时后触发
+    pub fn push_keymap(&mut self, keymap: String) {
+        let shadowing_keys: Vec<&BindingKey> = self
+            .bindings_info
+            .values()
+            .filter(|binding_info| binding_info.options.keymap.as_ref() == Some(&keymap))
+            .map(|binding_info| &binding_info.keys[0])
+            .collect();
+        let shadowed_matches: Vec<(u32, usize)> = self
+            .partial_matches
+            .iter()
+            .filter(|partial_match| {
+                let binding_info = &self.bindings_info[&partial_match.binding_uid];
+                let next_key = &binding_info.keys[partial_match.next_index];
+                binding_info.options.keymap.as_ref() != Some(&keymap)
+                    && shadowing_keys
+                        .iter()
+                        .any(|shadowing_key| is_same_binding_key(shadowing_key, next_key))
+            })
+            .map(|partial_match| (partial_match.binding_uid, partial_match.next_index))
+            .collect();
+        self.partial_matches.retain(|partial_match| {
+            !shadowed_matches.contains(&(partial_match.binding_uid, partial_match.next_index))
+        });
+        if let Some(pending_match) = &mut self.pending_match {
+            pending_match
+                .blocking_matches
+                .retain(|blocking_match| !shadowed_matches.contains(blocking_match));
+        }
+        self.keymaps.push(keymap);
+    }
+
+    /// 从栈中移除最上层的名为`keymap`的按键表，不一定是栈顶，
+    /// 不再参与匹配的绑定清除匹配状态
+    pub fn pop_keymap(&mut self, keymap: &str) {
+        let index = match self.keymaps.iter().rposition(|pushed| pushed == keymap) {
+            Some(index) => index,
+            None => return,
+        };
+        self.keymaps.remove(index);
+        let uids: HashSet<u32> = self
+            .bindings_info
+            .values()
+            .filter(|binding_info| self.get_layer(binding_info).is_none())
+            .map(|binding_info| binding_info.binding_uid)
+            .collect();
+        self.clear_match_state(&uids);
+    }
+
+    /// 绑定所在的层，最底层为0，按键表不在栈中时为`None`
+    fn get_layer(&self, binding_info: &BindingInfo) -> Option<usize> {
+        let keymap = match &binding_info.options.keymap {
+            Some(keymap) => keymap,
+            None => return Some(0),
+        };
+        self.keymaps
+            .iter()
+            .rposition(|pushed| pushed == keymap)
+            .map(|index| index + 1)
+    }
+
+    /// 绑定是否参与匹配
+    fn is_matching(&self, binding_info: &BindingInfo) -> bool {
+        !self.paused && binding_info.enabled && self.get_layer(binding_info).is_some()
+    }
+
+    /// 清除`uids`的部分匹配、按住、点按和等待的状态，之后需要从第一个按键重新匹配，
+    /// 按住的双重功能按键不再视为`DualRole::hold_as`
+    ///
+    /// 等待中的绑定通知取消，从不触发。只有阻塞它的更长的绑定被清除时，
+    /// 等待中的绑定继续等待，之后照常在下一个按键或者超时后触发
+    fn clear_match_state(&mut self, uids: &HashSet<u32>) {
+        if uids.is_empty() {
             return;
         }
-        let first_key = first_key.unwrap();
-        let bindings_of_keys = self.first_key_to_match.entry(first_key).or_default();
-        bindings_of_keys.insert(
-            binding_info.binding_uid,
-            Rc::new(RefCell::new(binding_info)),
-        );
+        self.partial_matches
+            .retain(|partial_match| !uids.contains(&partial_match.binding_uid));
+        self.held_matches
+            .retain(|held_match| !uids.contains(&held_match.binding_uid));
+        self.tap_counts.retain(|uid, _| !uids.contains(uid));
+        let holding_keys = &mut self.holding_keys;
+        let holding_physical_keys = &mut self.holding_physical_keys;
+        self.dual_role_downs.retain(|_, dual_role_down| {
+            if !uids.contains(&dual_role_down.binding_uid) {
+                return true;
+            }
+            holding_keys.remove(&dual_role_down.hold_as);
+            holding_physical_keys.remove(&dual_role_down.hold_as);
+            false
+        });
+        let pending_match = match &mut self.pending_match {
+            Some(pending_match) => pending_match,
+            None => return,
+        };
+        let bindings_info = &self.bindings_info;
+        pending_match.binding_uids.retain(|uid| {
+            if !uids.contains(uid) {
+                return true;
+            }
+            bindings_info[uid].notify(BindingNotice::Cancelled(*uid));
+            false
+        });
+        pending_match
+            .blocking_matches
+            .retain(|(binding_uid, _)| !uids.contains(binding_uid));
+        if pending_match.binding_uids.is_empty() {
+            self.pending_match = None;
+        }
     }
 
-    pub fn unbind(&mut self, uid: u32) {
-        let binding_info = self.bindings_info.remove(&uid);
-        if binding_info.is_none() {
+    /// 绑定的数量
+    pub fn get_binding_count(&self) -> usize {
+        self.bindings_info.len()
+    }
+
+    /// 没有任何绑定，也没有残留的匹配状态
+    pub fn is_empty(&self) -> bool {
+        self.bindings_info.is_empty()
+            && self.first_key_to_match.is_empty()
+            && self.partial_matches.is_empty()
+            && self.pending_match.is_none()
+            && self.held_matches.is_empty()
+            && self.tap_counts.is_empty()
+            && self.dual_role_keys.is_empty()
+            && self.dual_role_downs.is_empty()
+    }
+
+    /// `None`表示按键序列永不超时
+    pub fn set_default_sequence_timeout(&mut self, timeout: Option<Duration>) {
+        self.default_sequence_timeout = timeout;
+    }
+
+    pub fn set_ambiguous_timeout(&mut self, timeout: Duration) {
+        self.ambiguous_timeout = timeout;
+    }
+
+    /// 等待超时后触发等待中的绑定，按住足够时间后触发`Trigger::Hold`的绑定，
+    /// `now`和`InputKey::time`使用同一个时钟
+    pub fn on_tick(&mut self, now: u32) {
+        self.expire_sequences(now);
+        if !self.consumed_keys.is_empty() && !self.has_consuming_partial_match() {
+            let mut consumed_keys = self.take_consumed_keys();
+            self.replay_keys.append(&mut consumed_keys);
+        }
+        if let Some(pending_match) = &self.pending_match {
+            let elapsed = now.wrapping_sub(pending_match.time);
+            if Duration::from_millis(elapsed as u64) >= self.ambiguous_timeout {
+                self.fire_pending_match();
+            }
+        }
+        let deferred_uids: Vec<u32> = self
+            .tap_counts
+            .iter()
+            .filter(|(uid, tap_count)| {
+                let elapsed = now.wrapping_sub(tap_count.last_tap_time);
+                tap_count.deferred
+                    && Duration::from_millis(elapsed as u64)
+                        > get_tap_interval(&self.bindings_info[uid])
+            })
+            .map(|(uid, _)| *uid)
+            .collect();
+        for uid in deferred_uids {
+            self.fire_multi_tap(uid);
+        }
+        for held_match in &mut self.held_matches {
+            if is_pending(&self.pending_match, held_match.binding_uid) {
+                continue;
+            }
+            let binding_info = &self.bindings_info[&held_match.binding_uid];
+            let (duration, repeat) = match binding_info.options.trigger {
+                Trigger::Hold { duration, repeat } => (duration, repeat),
+                _ => continue,
+            };
+            let elapsed = Duration::from_millis(now.wrapping_sub(held_match.down_time) as u64);
+            // 触发次数追上按住的时长，tick间隔较长时一次触发多次
+            loop {
+                let due = match (held_match.triggered_count, repeat) {
+                    (0, _) => duration,
+                    (count, Some(repeat)) => duration + repeat.max(MIN_HOLD_REPEAT) * count,
+                    (_, None) => break,
+                };
+                if elapsed < due {
+                    break;
+                }
+                held_match.triggered_count += 1;
+                binding_info.notify(BindingNotice::Triggered(held_match.binding_uid));
+            }
+        }
+    }
+
+    /// 按键完全匹配后，开始等待`Trigger::Release`和`Trigger::Hold`的绑定，
+    /// 按住时按键自动重复不会重新开始计时
+    fn hold_match(&mut self, uid: u32, key: KeyCode, time: u32) {
+        let is_holding = self
+            .held_matches
+            .iter()
+            .any(|held_match| held_match.binding_uid == uid && held_match.key == key);
+        if !is_holding {
+            self.held_matches.push(HeldMatch {
+                binding_uid: uid,
+                key,
+                down_time: time,
+                triggered_count: 0,
+                interrupted: false,
+                released_time: None,
+            });
+        }
+    }
+
+    /// 松开按键时触发`Trigger::Release`的绑定，放弃还没有触发的`Trigger::Hold`的绑定，
+    /// 按住时按下了其它按键的`Trigger::MultiTap`的绑定不算点按
+    ///
+    /// 等待下一个按键的绑定先记录松开的时间，决定触发后再处理，见`Self::fire_pending_match`
+    fn release_held_matches(&mut self, key: KeyCode, time: u32) {
+        let pending_match = &self.pending_match;
+        let mut released_matches = Vec::new();
+        for held_match in std::mem::take(&mut self.held_matches) {
+            if held_match.key != key {
+                self.held_matches.push(held_match);
+            } else if is_pending(pending_match, held_match.binding_uid) {
+                self.held_matches.push(HeldMatch {
+                    released_time: Some(time),
+                    ..held_match
+                });
+            } else {
+                released_matches.push(held_match);
+            }
+        }
+        for held_match in released_matches {
+            self.on_held_match_released(held_match, time);
+        }
+    }
+
+    fn on_held_match_released(&mut self, held_match: HeldMatch, time: u32) {
+        let uid = held_match.binding_uid;
+        match self.bindings_info[&uid].options.trigger {
+            Trigger::Release => {
+                self.bindings_info[&uid].notify(BindingNotice::Triggered(uid));
+            }
+            Trigger::MultiTap { interval, .. } => {
+                // 按住太久不算点按
+                let held = time.wrapping_sub(held_match.down_time);
+                if !held_match.interrupted && Duration::from_millis(held as u64) <= interval {
+                    self.on_tap(uid, time);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// 记录`Trigger::MultiTap`绑定的一次点按，达到点按次数后触发或者等待更多次点按
+    fn on_tap(&mut self, uid: u32, time: u32) {
+        let binding_info = &self.bindings_info[&uid];
+        let (target_count, interval) = match binding_info.options.trigger {
+            Trigger::MultiTap { count, interval } => (count, interval),
+            _ => return,
+        };
+        let has_more_taps = self
+            .get_multi_tap_siblings(uid)
+            .into_iter()
+            .any(
+                |sibling_uid| match self.bindings_info[&sibling_uid].options.trigger {
+                    Trigger::MultiTap { count, .. } => count > target_count,
+                    _ => false,
+                },
+            );
+        let tap_count = self.tap_counts.entry(uid).or_default();
+        let elapsed = Duration::from_millis(time.wrapping_sub(tap_count.last_tap_time) as u64);
+        if tap_count.count > 0 && elapsed <= interval {
+            tap_count.count += 1;
+        } else {
+            tap_count.count = 1;
+        }
+        tap_count.last_tap_time = time;
+        tap_count.deferred = false;
+        if tap_count.count != target_count {
             return;
         }
-        let binding_info = binding_info.unwrap();
-        let binding_info = binding_info.borrow();
-        let first_key = binding_info.get_first_key();
-        if first_key.is_none() {
+        if has_more_taps {
+            tap_count.deferred = true;
             return;
         }
-        let first_key = first_key.unwrap();
-        if let Some(bindings_of_key) = self.first_key_to_match.get_mut(&first_key) {
-            bindings_of_key.remove(&binding_info.binding_uid);
+        self.fire_multi_tap(uid);
+    }
+
+    /// 触发`Trigger::MultiTap`的绑定，绑定了同样按键的点按次数都重新开始计算
+    fn fire_multi_tap(&mut self, uid: u32) {
+        for sibling_uid in self.get_multi_tap_siblings(uid) {
+            self.tap_counts.remove(&sibling_uid);
+        }
+        self.bindings_info[&uid].notify(BindingNotice::Triggered(uid));
+    }
+
+    /// 按下其它按键后，连续点按被打断，已经达到点按次数在等待的绑定直接触发
+    fn interrupt_multi_taps(&mut self, pressed_key: PressedKey) {
+        let bindings_info = &self.bindings_info;
+        let interrupted_uids: Vec<u32> = self
+            .tap_counts
+            .keys()
+            .filter(|uid| {
+                let binding_info = &bindings_info[uid];
+                let last_key = binding_info.keys.last().unwrap().key;
+                !last_key.matches(pressed_key.get(binding_info.options.key_identity))
+            })
+            .copied()
+            .collect();
+        for uid in interrupted_uids {
+            let tap_count = self.tap_counts.remove(&uid).unwrap();
+            if tap_count.deferred {
+                self.bindings_info[&uid].notify(BindingNotice::Triggered(uid));
+            }
+        }
+    }
+
+    /// 按住按键时按下了其它按键，例如Shift + A，松开时不再算作点按
+    fn interrupt_held_matches(&mut self, pressed_key: KeyCode) {
+        for held_match in &mut self.held_matches {
+            if held_match.key != pressed_key {
+                held_match.interrupted = true;
+            }
+        }
+    }
+
+    /// 绑定了同样按键的`Trigger::MultiTap`绑定，包括`uid`自己
+    fn get_multi_tap_siblings(&self, uid: u32) -> Vec<u32> {
+        let keys = &self.bindings_info[&uid].keys;
+        self.bindings_info
+            .values()
+            .filter(|binding_info| {
+                matches!(binding_info.options.trigger, Trigger::MultiTap { .. })
+                    && binding_info.keys == *keys
+            })
+            .map(|binding_info| binding_info.binding_uid)
+            .collect()
+    }
+
+    /// 触发等待中的绑定，放弃阻塞它的更长的部分匹配
+    ///
+    /// 不是`Trigger::Press`的绑定从此按自己的触发方式触发，等待期间已经松开的，
+    /// 按记录的松开时间处理
+    fn fire_pending_match(&mut self) {
+        let pending_match = match self.pending_match.take() {
+            Some(pending_match) => pending_match,
+            None => return,
+        };
+        self.partial_matches.retain(|partial_match| {
+            !pending_match
+                .blocking_matches
+                .contains(&(partial_match.binding_uid, partial_match.next_index))
+        });
+        for uid in &pending_match.binding_uids {
+            let binding_info = &self.bindings_info[uid];
+            if binding_info.options.trigger == Trigger::Press {
+                binding_info.notify(BindingNotice::Triggered(*uid));
+            } else {
+                binding_info.notify(BindingNotice::Resolved(*uid));
+            }
+        }
+        let (released_matches, held_matches): (Vec<HeldMatch>, Vec<HeldMatch>) =
+            std::mem::take(&mut self.held_matches)
+                .into_iter()
+                .partition(|held_match| {
+                    held_match.released_time.is_some()
+                        && pending_match.binding_uids.contains(&held_match.binding_uid)
+                });
+        self.held_matches = held_matches;
+        for held_match in released_matches {
+            let time = held_match.released_time.unwrap();
+            self.on_held_match_released(held_match, time);
+        }
+    }
+
+    /// 按下一个按键后，决定等待中的绑定是触发、取消还是继续等待
+    fn resolve_pending_match(&mut self, pressed_key: PressedKey) {
+        let pending_match = match &self.pending_match {
+            Some(pending_match) => pending_match,
+            None => return,
+        };
+        let is_continued = pending_match
+            .blocking_matches
+            .iter()
+            .any(|(uid, next_index)| {
+                self.bindings_info[uid].if_key_matched(
+                    *next_index,
+                    pressed_key,
+                    &self.holding_keys,
+                    &self.holding_physical_keys,
+                )
+            });
+        if is_continued {
+            let pending_match = self.pending_match.take().unwrap();
+            self.held_matches
+                .retain(|held_match| !pending_match.binding_uids.contains(&held_match.binding_uid));
+            for uid in pending_match.binding_uids {
+                self.bindings_info[&uid].notify(BindingNotice::Cancelled(uid));
+            }
+        } else if !pressed_key.key.is_modifier() {
+            self.fire_pending_match();
+        }
+    }
+
+    /// 双重功能按键按下和松开时，转换成`DualRole::hold_as`的按下和松开，
+    /// 松开时如果是点按，触发双重功能按键的绑定
+    ///
+    /// 返回值决定按键是否继续发送给其它程序，必须在输入钩子里同步决定
+    ///     * 按键匹配了`BindingOptions::consume`的绑定，或者推进了它的部分匹配，拦截按键，
+    ///       拦截了按下的按键，也拦截对应的松开
+    ///     * 部分匹配的按键序列失败后，重放拦截的按键，再加上当前按键
+    ///     * 部分匹配超时后，重放拦截的按键，见`Self::take_replay_keys`
+    pub fn on_input_key(&mut self, input_key: InputKey) -> InputDecision {
+        self.on_tick(input_key.time);
+        if input_key.opt == KeyOpt::Move {
+            // 鼠标移动不参与匹配
+            return InputDecision::Pass;
+        }
+        self.consumption = Consumption::None;
+        let dual_role_uid = self
+            .dual_role_keys
+            .get(&input_key.key)
+            .copied()
+            .filter(|uid| self.is_matching(&self.bindings_info[uid]));
+        if let Some(uid) = dual_role_uid {
+            self.on_dual_role_key(uid, input_key);
+        } else {
+            if input_key.opt == KeyOpt::Down {
+                self.interrupt_dual_role_downs();
+            }
+            self.on_mapped_input_key(input_key);
+        }
+        self.decide(input_key)
+    }
+
+    /// 部分匹配超时后需要重放的拦截的按键
+    pub fn take_replay_keys(&mut self) -> Vec<InputKey> {
+        std::mem::take(&mut self.replay_keys)
+    }
+
+    fn decide(&mut self, input_key: InputKey) -> InputDecision {
+        let consumption = std::mem::take(&mut self.consumption);
+        if input_key.opt == KeyOpt::Down && consumption != Consumption::None {
+            self.consumed_downs.insert(input_key.key);
+            if consumption == Consumption::Matched {
+                self.consumed_keys.clear();
+            } else {
+                self.consumed_keys.push(input_key);
+            }
+            return InputDecision::Consume;
+        }
+        if input_key.opt == KeyOpt::Up && self.consumed_downs.contains(&input_key.key) {
+            let is_undecided = self
+                .consumed_keys
+                .iter()
+                .any(|consumed_key| consumed_key.key == input_key.key);
+            if is_undecided {
+                self.consumed_keys.push(input_key);
+            } else {
+                self.consumed_downs.remove(&input_key.key);
+            }
+            return InputDecision::Consume;
+        }
+        if self.consumed_keys.is_empty() || self.has_consuming_partial_match() {
+            return InputDecision::Pass;
+        }
+        // 拦截了按键的部分匹配都失败了
+        let mut replay_keys = self.take_consumed_keys();
+        replay_keys.push(input_key);
+        InputDecision::Replace(replay_keys)
+    }
+
+    /// 取出拦截的按键用于重放，重放后还没松开的按键，松开时不再拦截
+    fn take_consumed_keys(&mut self) -> Vec<InputKey> {
+        let consumed_keys = std::mem::take(&mut self.consumed_keys);
+        for consumed_key in &consumed_keys {
+            self.consumed_downs.remove(&consumed_key.key);
+        }
+        consumed_keys
+    }
+
+    fn has_consuming_partial_match(&self) -> bool {
+        self.partial_matches.iter().any(|partial_match| {
+            self.bindings_info[&partial_match.binding_uid]
+                .options
+                .consume
+        })
+    }
+
+    fn on_dual_role_key(&mut self, uid: u32, input_key: InputKey) {
+        let binding_info = &self.bindings_info[&uid];
+        let dual_role = binding_info.options.dual_role.unwrap();
+        // 按住时作为左侧的修饰键
+        let hold_as = match dual_role.hold_as.get_sides() {
+            Some((left, _)) => left,
+            None => dual_role.hold_as,
+        };
+        let mapped_input_key = InputKey::new(hold_as, input_key.opt, input_key.time);
+        let consume = binding_info.options.consume;
+        if input_key.opt == KeyOpt::Down {
+            if self.dual_role_downs.contains_key(&input_key.key) {
+                // 按住时的自动重复
+                return;
+            }
+            self.interrupt_dual_role_downs();
+            self.dual_role_downs.insert(
+                input_key.key,
+                DualRoleDown {
+                    binding_uid: uid,
+                    hold_as,
+                    down_time: input_key.time,
+                    interrupted: false,
+                },
+            );
+            self.on_mapped_input_key(mapped_input_key);
+            if consume {
+                self.consumption = Consumption::Matched;
+            }
+        } else if input_key.opt == KeyOpt::Up {
+            let dual_role_down = match self.dual_role_downs.remove(&input_key.key) {
+                Some(dual_role_down) => dual_role_down,
+                None => return,
+            };
+            self.on_mapped_input_key(mapped_input_key);
+            if consume {
+                self.consumption = Consumption::Matched;
+            }
+            let held = input_key.time.wrapping_sub(dual_role_down.down_time);
+            if !dual_role_down.interrupted
+                && Duration::from_millis(held as u64) < dual_role.tapping_term
+            {
+                self.bindings_info[&uid].notify(BindingNotice::Triggered(uid));
+            }
         }
-        for (_, bindings_of_key) in &mut self.to_match_keys {
-            bindings_of_key.remove(&binding_info.binding_uid);
+    }
+
+    fn interrupt_dual_role_downs(&mut self) {
+        for dual_role_down in self.dual_role_downs.values_mut() {
+            dual_role_down.interrupted = true;
         }
     }
 
-    pub fn on_input_key(&mut self, input_key: InputKey) {
+    fn on_mapped_input_key(&mut self, input_key: InputKey) {
+        let pressed_key = PressedKey::from(&input_key);
         if input_key.opt == KeyOpt::Up {
             self.holding_keys.remove(&input_key.key);
+            self.holding_physical_keys.remove(&input_key.physical_key);
+            self.release_held_matches(input_key.key, input_key.time);
         } else if input_key.opt == KeyOpt::Down {
             self.holding_keys.insert(input_key.key);
-            self.update_next_match_keys(input_key.key);
+            self.holding_physical_keys.insert(input_key.physical_key);
+            self.interrupt_held_matches(input_key.key);
+            if !input_key.key.is_modifier() {
+                self.interrupt_multi_taps(pressed_key);
+            }
+            self.expire_sequences(input_key.time);
+            self.resolve_pending_match(pressed_key);
+            self.update_partial_matches(pressed_key, input_key.time);
         }
     }
 
-    /// self.first_key
-    ///     记录了首次可以匹配的按键
-    /// self.to_match_keys
-    ///     记录了下次可以匹配的按键，至少成功匹配过一个按键后该字段才会有值
-    ///     有两种情况该内容会被更新:
-    ///     * self.first_key匹配成功, 还有后续要匹配的按键, 例如组合按键：(Ctrl + A, Ctrl + B)
-    ///     * self.to_match_keys匹配成功，后续还有按键要继续匹配， 例如组合按键：(Ctrl + A, Ctrl + B, Ctrl + C)
+    /// 移除已经超时的部分匹配，这些绑定需要从第一个按键重新匹配，
+    /// 阻塞等待中的绑定的部分匹配都超时后，触发等待中的绑定
+    fn expire_sequences(&mut self, time: u32) {
+        let default_timeout = self.default_sequence_timeout;
+        let bindings_info = &self.bindings_info;
+        let mut expired_matches: Vec<(u32, usize)> = Vec::new();
+        self.partial_matches.retain(|partial_match| {
+            let binding_info = &bindings_info[&partial_match.binding_uid];
+            let is_expired = binding_info.is_sequence_expired(partial_match, time, default_timeout);
+            if is_expired {
+                expired_matches.push((partial_match.binding_uid, partial_match.next_index));
+            }
+            !is_expired
+        });
+        if expired_matches.is_empty() {
+            return;
+        }
+        let pending_match = match &mut self.pending_match {
+            Some(pending_match) => pending_match,
+            None => return,
+        };
+        pending_match
+            .blocking_matches
+            .retain(|blocking_match| !expired_matches.contains(blocking_match));
+        if pending_match.blocking_matches.is_empty() {
+            self.fire_pending_match();
+        }
+    }
+
+    /// 把所有绑定看作一个自动机，self.partial_matches记录了自动机当前所有的活动状态，
+    /// 每个状态是一个绑定已经匹配了的按键数
     ///
     /// 匹配规则
-    /// 根据self.to_match_keys是否为空来判断目前是否成功匹配过任何按键
-    ///     * 是，使用self.to_match_keys中的内容进行匹配
-    ///     * 否，使用self.first_key中的内容进行匹配
-    /// 当按键匹配成功后, 判断是否为组合按键
-    ///     * 是，把后一个要匹配的按键更新到self.to_match_keys
-    ///     * 否，匹配成功, 清空self.to_match_keys, 重新开始匹配
-    /// 当匹配失败后, 清空self.to_match_keys, 重新开始匹配
-    fn update_next_match_keys(&mut self, input_key: KeyCode) {
-        let mut bindings_of_key = None;
-        if self.to_match_keys.is_empty() {
-            bindings_of_key = self.first_key_to_match.get(&input_key);
-        } else {
-            bindings_of_key = self.to_match_keys.get(&input_key);
+    /// 每个按下的按键
+    ///     * 推进所有可以匹配的部分匹配
+    ///     * 同时作为首个按键尝试匹配self.first_key_to_match中的绑定，
+    ///       所以匹配失败的前缀可以从这个按键重新开始，例如(A, B)输入A, A, B
+    ///     * 不匹配的部分匹配被丢弃，但不匹配的修饰键不会打断部分匹配，
+    ///       例如(Ctrl + K, Ctrl + C)在两个按键之间重新按下Ctrl
+    /// 完全匹配的绑定不会影响其它部分匹配，例如(A, B)和(B, C)输入A, B, C时都会触发
+    /// 同一个按键使多个绑定完全匹配时，只触发按键序列最长的绑定，
+    /// 例如(A, B)和(B)输入A, B时只触发(A, B)
+    ///
+    /// 歧义前缀
+    /// 完全匹配的按键同时也是更长绑定的前缀时，例如(Ctrl + K)和(Ctrl + K, Ctrl + C)，
+    /// 先不触发，记录到self.pending_match等待下一个按键
+    ///     * 下一个按键继续匹配更长的绑定，取消等待中的绑定
+    ///     * 下一个按键不匹配，或者等待超过self.ambiguous_timeout，触发等待中的绑定
+    ///     * 不匹配的修饰键继续等待
+    /// 松开、按住和连续点按触发的绑定也一样等待，决定触发后才按自己的触发方式触发
+    ///
+    /// 按键表
+    /// 同一个按键可以开始匹配多层的绑定时，只开始匹配最上层的绑定，
+    /// 同一个按键使多层的绑定完全匹配时，只考虑最上层的绑定，
+    /// 上层没有绑定的按键继续匹配下层的绑定，见`Self::push_keymap`
+    ///
+    /// 物理按键的绑定登记在物理按键上，所以同时用逻辑按键和物理按键查找首个按键
+    fn update_partial_matches(&mut self, pressed_key: PressedKey, time: u32) {
+        let mut next_partial_matches: Vec<PartialMatch> = Vec::new();
+        let mut matched_uids: Vec<u32> = Vec::new();
+        let mut kept_partial_matches: Vec<PartialMatch> = Vec::new();
+        let mut advance = |binding_info: &BindingInfo, next_index: usize| {
+            if next_index == binding_info.keys.len() {
+                matched_uids.push(binding_info.binding_uid);
+                return;
+            }
+            let is_duplicate = next_partial_matches.iter().any(|partial_match| {
+                partial_match.binding_uid == binding_info.binding_uid
+                    && partial_match.next_index == next_index
+            });
+            if !is_duplicate {
+                next_partial_matches.push(PartialMatch {
+                    binding_uid: binding_info.binding_uid,
+                    next_index,
+                    last_match_time: time,
+                });
+            }
+        };
+        for partial_match in self.partial_matches.drain(..) {
+            let binding_info = &self.bindings_info[&partial_match.binding_uid];
+            let next_index = partial_match.next_index;
+            if binding_info.if_key_matched(
+                next_index,
+                pressed_key,
+                &self.holding_keys,
+                &self.holding_physical_keys,
+            ) {
+                advance(binding_info, next_index + 1);
+            } else if pressed_key.key.is_modifier() {
+                kept_partial_matches.push(partial_match);
+            }
         }
-        // 按键没有匹配到任何绑定，重新开始匹配
-        if bindings_of_key.is_none() {
-            self.to_match_keys.drain();
-            return;
+        let mut first_keys = vec![pressed_key.key];
+        if pressed_key.physical_key != pressed_key.key {
+            first_keys.push(pressed_key.physical_key);
         }
-        let bindings_of_key = bindings_of_key.unwrap();
-        let mut if_match_success = false;
-        let mut next_match_keys: HashMap<KeyCode, HashMap<u32, BindingInfoMutRc>> = HashMap::new();
-        for (uid, binding_info_rc) in bindings_of_key {
-            let mut binding_info = binding_info_rc.borrow_mut();
-            match binding_info.try_match(input_key, &self.holding_keys) {
-                MatchKeyResult::Matching(next_key) => {
-                    let next_match = next_match_keys.entry(next_key).or_default();
-                    next_match.insert(*uid, binding_info_rc.clone());
+        let mut started_uids: Vec<u32> = Vec::new();
+        for first_key in first_keys {
+            let bindings_of_key = match self.first_key_to_match.get(&first_key) {
+                Some(bindings_of_key) => bindings_of_key,
+                None => continue,
+            };
+            for uid in bindings_of_key {
+                let binding_info = &self.bindings_info[uid];
+                if !self.is_matching(binding_info) {
+                    continue;
                 }
-                MatchKeyResult::Success => {
-                    if_match_success = true;
+                // 另一种按键身份的绑定由if_key_matched排除
+                if binding_info.if_key_matched(
+                    0,
+                    pressed_key,
+                    &self.holding_keys,
+                    &self.holding_physical_keys,
+                ) {
+                    started_uids.push(*uid);
                 }
-                _ => {}
             }
         }
-        // 有成功匹配的绑定，重新开始匹配
-        if if_match_success {
-            self.to_match_keys.drain();
+        let top_layer = started_uids
+            .iter()
+            .filter_map(|uid| self.get_layer(&self.bindings_info[uid]))
+            .max();
+        for uid in started_uids {
+            let binding_info = &self.bindings_info[&uid];
+            if self.get_layer(binding_info) == top_layer {
+                advance(binding_info, 1);
+            }
+        }
+        let kept_len = kept_partial_matches.len();
+        let is_consuming = next_partial_matches.iter().any(|partial_match| {
+            self.bindings_info[&partial_match.binding_uid]
+                .options
+                .consume
+        });
+        if is_consuming {
+            self.consumption = Consumption::Partial;
+        }
+        next_partial_matches.append(&mut kept_partial_matches);
+        self.partial_matches = next_partial_matches;
+
+        let top_layer = matched_uids
+            .iter()
+            .filter_map(|uid| self.get_layer(&self.bindings_info[uid]))
+            .max();
+        matched_uids.retain(|uid| self.get_layer(&self.bindings_info[uid]) == top_layer);
+
+        let longest_len = match matched_uids
+            .iter()
+            .map(|uid| self.bindings_info[uid].keys.len())
+            .max()
+        {
+            Some(longest_len) => longest_len,
+            None => return,
+        };
+        matched_uids.retain(|uid| self.bindings_info[uid].keys.len() == longest_len);
+        if matched_uids
+            .iter()
+            .any(|uid| self.bindings_info[uid].options.consume)
+        {
+            self.consumption = Consumption::Matched;
+        }
+        // 按下不匹配的修饰键时可能还在等待
+        self.fire_pending_match();
+        for uid in &matched_uids {
+            if self.bindings_info[uid].options.trigger != Trigger::Press {
+                self.hold_match(*uid, pressed_key.key, time);
+            }
+        }
+        let blocking_matches: Vec<(u32, usize)> = self.partial_matches
+            [..self.partial_matches.len() - kept_len]
+            .iter()
+            .filter(|partial_match| partial_match.next_index >= longest_len)
+            .map(|partial_match| (partial_match.binding_uid, partial_match.next_index))
+            .collect();
+        if blocking_matches.is_empty() {
+            for uid in matched_uids {
+                let binding_info = &self.bindings_info[&uid];
+                if binding_info.options.trigger == Trigger::Press {
+                    binding_info.notify(BindingNotice::Triggered(uid));
+                }
+            }
             return;
         }
-        self.to_match_keys = next_match_keys;
+        for uid in &matched_uids {
+            self.bindings_info[uid].notify(BindingNotice::Pending(*uid));
+        }
+        self.pending_match = Some(PendingMatch {
+            binding_uids: matched_uids,
+            blocking_matches,
+            time,
+        });
+    }
+}
+
+fn is_pending(pending_match: &Option<PendingMatch>, uid: u32) -> bool {
+    pending_match
+        .as_ref()
+        .is_some_and(|pending_match| pending_match.binding_uids.contains(&uid))
+}
+
+/// 两个绑定按键能否被同一次按键匹配，左右不限的按键和任意一侧的按键视为相同
+fn is_same_binding_key(a: &BindingKey, b: &BindingKey) -> bool {
+    let is_same_key = |a: KeyCode, b: KeyCode| a.matches(b) || b.matches(a);
+    is_same_key(a.key, b.key)
+        && a.modifer_keys.len() == b.modifer_keys.len()
+        && a.modifer_keys.iter().all(|a_modifer| {
+            b.modifer_keys
+                .iter()
+                .any(|b_modifer| is_same_key(*a_modifer, *b_modifer))
+        })
+}
+
+/// 左右不限的按键要同时登记在左右两个按键上
+fn get_physical_keys(key: KeyCode) -> Vec<KeyCode> {
+    match key.get_sides() {
+        Some((left, right)) => vec![left, right],
+        None => vec![key],
+    }
+}
+
+fn get_tap_interval(binding_info: &BindingInfo) -> Duration {
+    match binding_info.options.trigger {
+        Trigger::MultiTap { interval, .. } => interval,
+        _ => Duration::ZERO,
     }
 }
@@ -0,0 +1,251 @@
+use crate::binding_key_mgr::BindingKey;
+use crate::virtual_key::KeyCode;
+use std::fmt::Display;
+use std::str::FromStr;
+
+/// Names accepted for a key, besides its `KeyCode` variant name. The first one is
+/// used when formatting. All names are matched case-insensitively.
+const KEY_NAMES: &[(KeyCode, &[&str])] = &[
+    (KeyCode::Escape, &["Esc"]),
+    (KeyCode::Backquote, &["`", "Grave"]),
+    (KeyCode::Num1, &["1"]),
+    (KeyCode::Num2, &["2"]),
+    (KeyCode::Num3, &["3"]),
+    (KeyCode::Num4, &["4"]),
+    (KeyCode::Num5, &["5"]),
+    (KeyCode::Num6, &["6"]),
+    (KeyCode::Num7, &["7"]),
+    (KeyCode::Num8, &["8"]),
+    (KeyCode::Num9, &["9"]),
+    (KeyCode::Num0, &["0"]),
+    (KeyCode::Minus, &["-"]),
+    (KeyCode::Equal, &["="]),
+    (KeyCode::LeftBracket, &["["]),
+    (KeyCode::RightBracket, &["]"]),
+    (KeyCode::Backslash, &["\\"]),
+    (KeyCode::Capslock, &["CapsLock", "Caps"]),
+    (KeyCode::Semicolon, &[";"]),
+    (KeyCode::Quote, &["'", "Apostrophe"]),
+    (KeyCode::Enter, &["Enter", "Return"]),
+    (KeyCode::Dot, &[".", "Period"]),
+    (KeyCode::Slash, &["/"]),
+    (KeyCode::Control, &["Ctrl", "Control"]),
+    (KeyCode::ControlLeft, &["LCtrl", "LeftCtrl"]),
+    (KeyCode::ControlRight, &["RCtrl", "RightCtrl"]),
+    (KeyCode::Shift, &["Shift"]),
+    (KeyCode::ShiftLeft, &["LShift", "LeftShift"]),
+    (KeyCode::ShiftRight, &["RShift", "RightShift"]),
+    (KeyCode::Alt, &["Alt", "Option"]),
+    (KeyCode::AltLeft, &["LAlt", "LeftAlt"]),
+    (KeyCode::AltRight, &["RAlt", "RightAlt", "AltGr"]),
+    (KeyCode::Meta, &["Meta", "Win", "Super", "Cmd", "Command"]),
+    (KeyCode::MetaLeft, &["LMeta", "LWin", "LeftMeta"]),
+    (KeyCode::MetaRight, &["RMeta", "RWin", "RightMeta"]),
+    (KeyCode::Printscreen, &["PrintScreen", "PrtSc"]),
+    (KeyCode::Pause, &["Pause", "Break"]),
+    (KeyCode::Insert, &["Insert", "Ins"]),
+    (KeyCode::Delete, &["Delete", "Del"]),
+    (KeyCode::PageUp, &["PageUp", "PgUp"]),
+    (KeyCode::PageDown, &["PageDown", "PgDn"]),
+    (KeyCode::UpArrow, &["Up"]),
+    (KeyCode::DownArrow, &["Down"]),
+    (KeyCode::LeftArrow, &["Left"]),
+    (KeyCode::RightArrow, &["Right"]),
+    (KeyCode::NumpadAdd, &["NumpadAdd", "NumpadPlus"]),
+    (KeyCode::NumpadSubtract, &["NumpadSubtract", "NumpadMinus"]),
+    (KeyCode::NumpadMultiply, &["NumpadMultiply", "NumpadStar"]),
+    (KeyCode::NumpadDecimal, &["NumpadDecimal", "NumpadDot"]),
+    (KeyCode::ContextMenu, &["Menu", "Apps"]),
+    (KeyCode::VolumeMute, &["Mute"]),
+    (KeyCode::MediaPlayPause, &["PlayPause"]),
+    (KeyCode::MediaNextTrack, &["NextTrack"]),
+    (KeyCode::MediaPrevTrack, &["PrevTrack"]),
+    (KeyCode::MouseX1, &["X1", "XButton1", "Mouse4"]),
+    (KeyCode::MouseX2, &["X2", "XButton2", "Mouse5"]),
+    (KeyCode::WheelUp, &["WheelUp", "ScrollUp"]),
+    (KeyCode::WheelDown, &["WheelDown", "ScrollDown"]),
+    (KeyCode::WheelLeft, &["WheelLeft", "ScrollLeft"]),
+    (KeyCode::WheelRight, &["WheelRight", "ScrollRight"]),
+];
+
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum ParseErrorKind {
+    /// nothing but whitespace between separators, e.g. `Ctrl++K` or `Ctrl+K,`
+    EmptyToken,
+    UnknownKey,
+    /// the same key listed twice in one binding key, e.g. `Ctrl+Ctrl+K`
+    DuplicateKey,
+}
+
+/// Why a hotkey string could not be parsed, and where.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct ParseError {
+    kind: ParseErrorKind,
+    input: String,
+    position: usize,
+    token: String,
+}
+
+impl ParseError {
+    fn new(kind: ParseErrorKind, input: &str, position: usize, token: &str) -> Self {
+        Self {
+            kind,
+            input: input.to_string(),
+            position,
+            token: token.to_string(),
+        }
+    }
+
+    pub fn kind(&self) -> &ParseErrorKind {
+        &self.kind
+    }
+
+    /// byte offset of the bad token in the parsed string
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    pub fn token(&self) -> &str {
+        &self.token
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.kind {
+            ParseErrorKind::EmptyToken => write!(f, "missing key")?,
+            ParseErrorKind::UnknownKey => write!(f, "unknown key `{}`", self.token)?,
+            ParseErrorKind::DuplicateKey => write!(f, "duplicate key `{}`", self.token)?,
+        }
+        write!(f, " at position {} in `{}`", self.position, self.input)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Parses a sequence of binding keys separated by `,`, such as `"Ctrl+Shift+K, Ctrl+Shift+C"`.
+pub fn parse_sequence(input: &str) -> Result<Vec<BindingKey>, ParseError> {
+    let mut binding_keys = Vec::new();
+    let mut offset = 0;
+    for part in input.split(',') {
+        binding_keys.push(parse_binding_key(input, part, offset)?);
+        offset += part.len() + 1;
+    }
+    Ok(binding_keys)
+}
+
+/// Formats a sequence the way `parse_sequence` reads it.
+pub fn format_sequence(binding_keys: &[BindingKey]) -> String {
+    binding_keys
+        .iter()
+        .map(|binding_key| binding_key.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Parses `part`, which starts at byte `offset` of `input`. Every `+` separated token
+/// but the last is a modifer key.
+fn parse_binding_key(input: &str, part: &str, offset: usize) -> Result<BindingKey, ParseError> {
+    let mut keys: Vec<KeyCode> = Vec::new();
+    let mut token_offset = offset;
+    for raw_token in part.split('+') {
+        let token = raw_token.trim();
+        let position = token_offset + (raw_token.len() - raw_token.trim_start().len());
+        token_offset += raw_token.len() + 1;
+        if token.is_empty() {
+            return Err(ParseError::new(
+                ParseErrorKind::EmptyToken,
+                input,
+                position,
+                token,
+            ));
+        }
+        let key = match parse_key_name(token) {
+            Some(key) => key,
+            None => {
+                return Err(ParseError::new(
+                    ParseErrorKind::UnknownKey,
+                    input,
+                    position,
+                    token,
+                ))
+            }
+        };
+        if keys.contains(&key) {
+            return Err(ParseError::new(
+                ParseErrorKind::DuplicateKey,
+                input,
+                position,
+                token,
+            ));
+        }
+        keys.push(key);
+    }
+    let key = keys.pop().unwrap();
+    Ok(BindingKey {
+        key,
+        modifer_keys: keys,
+    })
+}
+
+fn parse_key_name(name: &str) -> Option<KeyCode> {
+    for (key, names) in KEY_NAMES {
+        if names.iter().any(|alias| alias.eq_ignore_ascii_case(name)) {
+            return Some(*key);
+        }
+    }
+    let mut chars = name.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        if c.is_ascii_alphabetic() {
+            let variant_name = format!("Key{}", c.to_ascii_uppercase());
+            return KeyCode::known_keys().find(|key| key.to_str() == variant_name);
+        }
+    }
+    KeyCode::known_keys().find(|key| key.to_str().eq_ignore_ascii_case(name))
+}
+
+impl KeyCode {
+    /// The name used when formatting binding keys, e.g. `Ctrl` or `A`.
+    pub fn to_name(&self) -> String {
+        if let KeyCode::Unknown(id) = self {
+            return format!("Unknown({})", id);
+        }
+        if let Some((_, names)) = KEY_NAMES.iter().find(|(key, _)| key == self) {
+            return names[0].to_string();
+        }
+        let name = self.to_str();
+        match name.strip_prefix("Key") {
+            Some(letter) if letter.len() == 1 => letter.to_string(),
+            _ => name.to_string(),
+        }
+    }
+}
+
+impl FromStr for KeyCode {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.trim();
+        let position = s.len() - s.trim_start().len();
+        parse_key_name(name)
+            .ok_or_else(|| ParseError::new(ParseErrorKind::UnknownKey, s, position, name))
+    }
+}
+
+impl FromStr for BindingKey {
+    type Err = ParseError;
+
+    /// Parses a single binding key such as `"Ctrl+Shift+K"`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_binding_key(s, s, 0)
+    }
+}
+
+impl Display for BindingKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for modifer_key in &self.modifer_keys {
+            write!(f, "{}+", modifer_key.to_name())?;
+        }
+        write!(f, "{}", self.key.to_name())
+    }
+}
@@ -0,0 +1,127 @@
+//! Diagnostics through the `tracing` facade, compiled in with the `tracing` feature.
+//!
+//! Every call site of `diag!` is rate-limited on its own, so that a flood of the same
+//! event, such as an unknown message from the hooks, can't flood the log. The number
+//! of events dropped meanwhile is reported as the `suppressed` field of the next one.
+//! Events of disabled levels are filtered out first, so they don't count.
+
+#[cfg(feature = "tracing")]
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+#[cfg(feature = "tracing")]
+use std::sync::OnceLock;
+#[cfg(feature = "tracing")]
+use std::time::Instant;
+
+/// events of one call site let through per `RATE_LIMIT_INTERVAL_MS`
+#[cfg(feature = "tracing")]
+const RATE_LIMIT_BURST: u32 = 10;
+#[cfg(feature = "tracing")]
+const RATE_LIMIT_INTERVAL_MS: u64 = 1000;
+
+#[cfg(feature = "tracing")]
+pub(crate) struct RateLimit {
+    window_start: AtomicU64, // milliseconds since `epoch()`
+    count: AtomicU32,
+    suppressed: AtomicU32,
+}
+
+#[cfg(feature = "tracing")]
+impl RateLimit {
+    pub const fn new() -> Self {
+        Self {
+            window_start: AtomicU64::new(0),
+            count: AtomicU32::new(0),
+            suppressed: AtomicU32::new(0),
+        }
+    }
+
+    /// `Some` with the number of events suppressed since the last one let through,
+    /// or `None` if this event is suppressed
+    pub fn check(&self) -> Option<u32> {
+        static EPOCH: OnceLock<Instant> = OnceLock::new();
+        let now = EPOCH.get_or_init(Instant::now).elapsed().as_millis() as u64;
+        let window_start = self.window_start.load(Ordering::Relaxed);
+        if now.saturating_sub(window_start) >= RATE_LIMIT_INTERVAL_MS
+            && self
+                .window_start
+                .compare_exchange(window_start, now, Ordering::Relaxed, Ordering::Relaxed)
+                .is_ok()
+        {
+            self.count.store(0, Ordering::Relaxed);
+        }
+        if self.count.fetch_add(1, Ordering::Relaxed) < RATE_LIMIT_BURST {
+            return Some(self.suppressed.swap(0, Ordering::Relaxed));
+        }
+        self.suppressed.fetch_add(1, Ordering::Relaxed);
+        None
+    }
+}
+
+/// `diag!(warn, "open input device:{} failed, {}", path, e)`, with any `tracing` level
+#[cfg(feature = "tracing")]
+macro_rules! diag {
+    ($level:ident, $($arg:tt)+) => {{
+        static RATE_LIMIT: $crate::diagnostics::RateLimit = $crate::diagnostics::RateLimit::new();
+        if tracing::enabled!($crate::diagnostics::level!($level)) {
+            match RATE_LIMIT.check() {
+                Some(0) => tracing::$level!($($arg)+),
+                Some(suppressed) => tracing::$level!(suppressed, $($arg)+),
+                None => (),
+            }
+        }
+    }};
+}
+
+/// the `tracing::Level` of a level name of `diag!`
+#[cfg(feature = "tracing")]
+macro_rules! level {
+    (trace) => {
+        tracing::Level::TRACE
+    };
+    (debug) => {
+        tracing::Level::DEBUG
+    };
+    (info) => {
+        tracing::Level::INFO
+    };
+    (warn) => {
+        tracing::Level::WARN
+    };
+    (error) => {
+        tracing::Level::ERROR
+    };
+}
+
+#[cfg(not(feature = "tracing"))]
+macro_rules! diag {
+    ($level:ident, $($arg:tt)+) => {{
+        if false {
+            let _ = format_args!($($arg)+);
+        }
+    }};
+}
+
+/// Enters a span with the given fields until the returned guard is dropped,
+/// e.g. `let _span = enter_span!("binding", uid = uid);`
+#[cfg(feature = "tracing")]
+macro_rules! enter_span {
+    ($name:literal, $($field:tt)+) => {
+        tracing::debug_span!($name, $($field)+).entered()
+    };
+}
+
+#[cfg(not(feature = "tracing"))]
+macro_rules! enter_span {
+    ($name:literal, $($field:tt)+) => {
+        $crate::diagnostics::NoSpan
+    };
+}
+
+/// the guard of `enter_span!` without the `tracing` feature
+#[cfg(not(feature = "tracing"))]
+pub(crate) struct NoSpan;
+
+pub(crate) use diag;
+pub(crate) use enter_span;
+#[cfg(feature = "tracing")]
+pub(crate) use level;
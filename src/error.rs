@@ -0,0 +1,56 @@
+use std::fmt::Display;
+use std::io;
+
+/// Why an operation of the listener, a binding or the simulator failed.
+#[derive(Debug)]
+pub enum Error {
+    /// installing the input hooks, or opening the input devices, failed
+    HookInstall(io::Error),
+    /// the listener was never started, or has stopped
+    ListenerNotRunning,
+    /// the binding with this uid conflicts with an existing one, e.g. a second
+    /// dual-role binding of the same key
+    DuplicateBinding(u32),
+    /// no binding or remap of this proxy has this uid
+    UnknownBinding(u32),
+    /// the binding can never match, e.g. it has no keys
+    InvalidBinding(String),
+    /// the backend can't keep input from other applications, which remaps and
+    /// `BindingOptions::consume` need, see `InputBackend::can_consume`
+    ConsumeUnsupported,
+    /// reading or sending input failed
+    Io(io::Error),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::HookInstall(e) => write!(f, "install input hooks failed, {}", e),
+            Error::ListenerNotRunning => write!(f, "the listener isn't running"),
+            Error::DuplicateBinding(uid) => {
+                write!(f, "binding uid:{} conflicts with an existing binding", uid)
+            }
+            Error::UnknownBinding(uid) => write!(f, "no binding with uid:{}", uid),
+            Error::InvalidBinding(reason) => write!(f, "invalid binding, {}", reason),
+            Error::ConsumeUnsupported => {
+                write!(f, "the input backend can't consume input")
+            }
+            Error::Io(e) => write!(f, "input i/o failed, {}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::HookInstall(e) | Error::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(value: io::Error) -> Self {
+        Error::Io(value)
+    }
+}
@@ -1,7 +1,36 @@
+pub(crate) mod backend;
 pub(crate) mod binding_key_mgr;
+pub(crate) mod binding_key_parser;
+pub(crate) mod diagnostics;
+pub(crate) mod error;
 pub(crate) mod listener;
+pub(crate) mod raw_event;
+pub(crate) mod remapper;
+pub(crate) mod scripted_listener;
+pub(crate) mod simulator;
 pub(crate) mod virtual_key;
 
-pub use binding_key_mgr::BindingKey;
-pub use listener::{start_listen, stop_listen};
-pub use virtual_key::KeyCode;
+#[cfg(target_os = "linux")]
+pub use backend::{EvdevBackend, UinputBackend, UINPUT_DEVICE_NAME};
+pub use backend::{
+    InputBackend, InputDecision, RecordingBackend, Script, ScriptedBackend, ScriptedInput,
+    SimulateBackend, SimulatedEvent,
+};
+#[cfg(windows)]
+pub use backend::{Win32Backend, Win32SimulateBackend};
+pub use binding_key_mgr::{
+    BindingKey, BindingOptions, DualRole, KeyIdentity, MatchPolicy, Trigger,
+    DEFAULT_AMBIGUOUS_TIMEOUT, DEFAULT_SEQUENCE_TIMEOUT, DEFAULT_TAPPING_TERM,
+    DEFAULT_TAP_INTERVAL,
+};
+pub use binding_key_parser::{format_sequence, parse_sequence, ParseError, ParseErrorKind};
+pub use error::Error;
+#[cfg(any(windows, target_os = "linux"))]
+pub use listener::start_listen;
+pub use listener::{start_listen_with, stop_listen, BindingHandle, ListenerProxy};
+pub use raw_event::{
+    DeviceKind, RawEvent, RawEventFilter, RawSubscription, DEFAULT_RAW_EVENT_CAPACITY,
+};
+pub use scripted_listener::ScriptedListener;
+pub use simulator::Simulator;
+pub use virtual_key::{CursorPos, InputKey, KeyCode, KeyOpt, WHEEL_NOTCH};
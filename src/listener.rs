@@ -1,56 +1,108 @@
+use crate::backend::{InputBackend, InputDecision};
 use crate::binding_key_mgr::*;
-use crate::virtual_key::*;
-use std::collections::HashMap;
-use std::ptr::null_mut;
+use crate::diagnostics::{diag, enter_span};
+use crate::error::Error;
+use crate::raw_event::{RawEventFilter, RawEventHub, RawSubscriber, RawSubscription};
+use crate::remapper::{RemapInfo, Remapper};
+use crate::virtual_key::{InputKey, KeyCode};
+use std::collections::{HashMap, HashSet};
+use std::io;
 use std::sync::{
+    atomic::{AtomicBool, Ordering},
     mpsc::{channel, Receiver, Sender, TryRecvError},
-    Mutex, OnceLock,
+    Arc, Mutex,
 };
 use std::thread::{self, JoinHandle};
-use windows::Win32::Foundation::{HMODULE, LPARAM, LRESULT, WPARAM};
-use windows::Win32::UI::WindowsAndMessaging::{
-    CallNextHookEx, PeekMessageW, SetWindowsHookExW, UnhookWindowsHookEx, HC_ACTION, HHOOK, MSG,
-    PM_REMOVE, WH_KEYBOARD_LL, WH_MOUSE_LL, WM_MOUSEMOVE,
-};
+use std::time::Duration;
 
-static mut LISTENER: OnceLock<Listener> = OnceLock::new();
-static LISTENER_THREAD: Mutex<OnceLock<JoinHandle<()>>> = Mutex::new(OnceLock::new());
-static LISTENER_OPT_TX: OnceLock<Sender<ListenerOpt>> = OnceLock::new();
-static mut KEYBOARD_HOOK: HHOOK = HHOOK(null_mut());
-static mut MOUSE_HOOK: HHOOK = HHOOK(null_mut());
-
-pub fn start_listen() -> ListenerProxy {
-    if let Some(listener_opt_tx) = LISTENER_OPT_TX.get() {
-        return ListenerProxy::new(listener_opt_tx.clone());
-    }
-    let (binding_opt_tx, binding_opt_rx) = channel();
-    let join_handle = thread::spawn(move || unsafe {
-        LISTENER.get_or_init(|| Listener::new(binding_opt_rx));
-        let listener = LISTENER.get_mut().unwrap();
-        listener.thread_loop();
-    });
-    LISTENER_THREAD
-        .lock()
-        .unwrap()
-        .get_or_init(move || join_handle);
-    let binding_opt_tx1 = binding_opt_tx.clone();
-    LISTENER_OPT_TX.get_or_init(move || binding_opt_tx1);
-    let proxy = ListenerProxy::new(binding_opt_tx);
-    proxy
+/// the listener thread started by `start_listen_with`, until `stop_listen`
+struct RunningListener {
+    join_handle: JoinHandle<Result<(), Error>>,
+    listener_opt_tx: Sender<ListenerOpt>,
+    can_consume: bool,
+}
+
+static RUNNING_LISTENER: Mutex<Option<RunningListener>> = Mutex::new(None);
+
+#[cfg(any(windows, target_os = "linux"))]
+pub fn start_listen() -> Result<ListenerProxy, Error> {
+    start_listen_with(crate::backend::default_backend())
 }
 
-pub fn stop_listen() {
-    if let Some(opt_tx) = LISTENER_OPT_TX.get() {
-        let _ = opt_tx.send(ListenerOpt::StopListen);
+/// Starts the listener thread, or returns another proxy of the running one.
+/// Waits until the backend is installed, so that failing to install is reported here.
+///
+/// The listener can be started again after `stop_listen`, or after its thread stopped
+/// on an error, with new proxies. Proxies of the stopped listener keep failing with
+/// `Error::ListenerNotRunning`.
+pub fn start_listen_with(backend: Box<dyn InputBackend>) -> Result<ListenerProxy, Error> {
+    let mut running_listener = RUNNING_LISTENER.lock().unwrap();
+    if let Some(running) = running_listener.as_ref() {
+        if !running.join_handle.is_finished() {
+            return Ok(ListenerProxy::new(
+                running.listener_opt_tx.clone(),
+                running.can_consume,
+            ));
+        }
+        // stopped on an error, which the thread has logged
+        let _ = running_listener.take().unwrap().join_handle.join();
     }
-    if let Some(join_handle) = LISTENER_THREAD.lock().unwrap().take() {
-        let _ = join_handle.join();
+    let can_consume = backend.can_consume();
+    let (listener_opt_tx, listener_opt_rx) = channel();
+    let (installed_tx, installed_rx) = channel();
+    let join_handle = thread::spawn(move || {
+        let mut listener = Listener::new(backend, listener_opt_rx);
+        if let Err(e) = listener.install_backend() {
+            let _ = installed_tx.send(Err(e));
+            return Ok(());
+        }
+        let _ = installed_tx.send(Ok(()));
+        listener.thread_loop()
+    });
+    match installed_rx.recv() {
+        Ok(Ok(())) => (),
+        Ok(Err(e)) => {
+            let _ = join_handle.join();
+            return Err(Error::HookInstall(e));
+        }
+        Err(_) => return Err(Error::ListenerNotRunning),
+    }
+    *running_listener = Some(RunningListener {
+        join_handle,
+        listener_opt_tx: listener_opt_tx.clone(),
+        can_consume,
+    });
+    Ok(ListenerProxy::new(listener_opt_tx, can_consume))
+}
+
+/// Stops the listener thread and waits for it, returning the error that stopped it
+/// early, if any.
+pub fn stop_listen() -> Result<(), Error> {
+    let running = match RUNNING_LISTENER.lock().unwrap().take() {
+        Some(running) => running,
+        None => return Err(Error::ListenerNotRunning),
+    };
+    let _ = running.listener_opt_tx.send(ListenerOpt::StopListen);
+    match running.join_handle.join() {
+        Ok(result) => result,
+        // the thread panicked
+        Err(_) => Err(Error::ListenerNotRunning),
     }
 }
 
-enum ListenerOpt {
+pub(crate) enum ListenerOpt {
     Bind(BindingInfo),
     Unbind(u32),
+    SetEnabled(u32, bool),
+    SetGroupEnabled(String, bool),
+    SetPaused(bool),
+    PushKeymap(String),
+    PopKeymap(String),
+    SetDefaultSequenceTimeout(Option<Duration>),
+    SetAmbiguousTimeout(Duration),
+    Remap(RemapInfo),
+    Unremap(u32),
+    SubscribeRaw(RawSubscriber),
     StopListen,
 }
 
@@ -59,31 +111,102 @@ enum BindingCallback {
     Multi(Box<dyn FnMut()>),
 }
 
+struct BoundCallback {
+    callback: BindingCallback,
+    active: Arc<AtomicBool>, // shared with the `BindingHandle` and the listener
+}
+
+/// A binding made through a `ListenerProxy`. Dropping the handle unbinds it,
+/// unless it was detached.
+#[must_use = "the binding is removed as soon as its handle is dropped, see `BindingHandle::detach`"]
+pub struct BindingHandle {
+    uid: u32,
+    active: Arc<AtomicBool>,
+    binding_opt_tx: Option<Sender<ListenerOpt>>, // `None` once detached or unbound
+}
+
+impl BindingHandle {
+    fn new(uid: u32, active: Arc<AtomicBool>, binding_opt_tx: Sender<ListenerOpt>) -> Self {
+        Self {
+            uid,
+            active,
+            binding_opt_tx: Some(binding_opt_tx),
+        }
+    }
+
+    pub fn uid(&self) -> u32 {
+        self.uid
+    }
+
+    /// Whether the binding may still trigger. It's inactive once unbound, once the
+    /// listener rejected it, or once a `bind_once` binding has been called back.
+    pub fn is_active(&self) -> bool {
+        self.active.load(Ordering::SeqCst)
+    }
+
+    /// Removes the binding. Its callback is never called again, even if it
+    /// triggered before and `ListenerProxy::update` wasn't called since.
+    pub fn unbind(mut self) -> Result<(), Error> {
+        match self.binding_opt_tx.take() {
+            Some(binding_opt_tx) if self.active.swap(false, Ordering::SeqCst) => binding_opt_tx
+                .send(ListenerOpt::Unbind(self.uid))
+                .map_err(|_| Error::ListenerNotRunning),
+            _ => Err(Error::UnknownBinding(self.uid)),
+        }
+    }
+
+    /// Keeps the binding after the handle is dropped, and returns its uid for
+    /// `ListenerProxy::unbind`.
+    pub fn detach(mut self) -> u32 {
+        self.binding_opt_tx = None;
+        self.uid
+    }
+}
+
+impl Drop for BindingHandle {
+    fn drop(&mut self) {
+        if let Some(binding_opt_tx) = self.binding_opt_tx.take() {
+            if self.active.swap(false, Ordering::SeqCst) {
+                let _ = binding_opt_tx.send(ListenerOpt::Unbind(self.uid));
+            }
+        }
+    }
+}
+
 pub struct ListenerProxy {
     binding_opt_tx: Sender<ListenerOpt>,
-    binding_notifier_tx: Sender<u32>,
-    binding_notifier_rx: Receiver<u32>,
-    callbacks: HashMap<u32, BindingCallback>,
+    binding_notifier_tx: Sender<BindingNotice>,
+    binding_notifier_rx: Receiver<BindingNotice>,
+    callbacks: HashMap<u32, BoundCallback>,
+    pending_uids: HashSet<u32>,
+    remap_uids: HashSet<u32>,
+    can_consume: bool, // see `InputBackend::can_consume`
 }
 
 impl ListenerProxy {
-    fn new(binding_opt_tx: Sender<ListenerOpt>) -> Self {
+    pub(crate) fn new(binding_opt_tx: Sender<ListenerOpt>, can_consume: bool) -> Self {
         let (binding_notifier_tx, binding_notifier_rx) = channel();
         Self {
             binding_opt_tx,
             binding_notifier_tx,
             binding_notifier_rx,
             callbacks: HashMap::new(),
+            pending_uids: HashSet::new(),
+            remap_uids: HashSet::new(),
+            can_consume,
         }
     }
 
-    fn fork(&self) -> Self {
+    pub fn fork(&self) -> Self {
         let (binding_notifier_tx, binding_notifier_rx) = channel();
         Self {
             binding_opt_tx: self.binding_opt_tx.clone(),
             binding_notifier_tx,
             binding_notifier_rx,
             callbacks: HashMap::new(),
+            pending_uids: HashSet::new(),
+            remap_uids: HashSet::new(),
+            can_consume: self.can_consume,
         }
     }
 
@@ -91,144 +214,435 @@ impl ListenerProxy {
         &mut self,
         binding_keys: Vec<BindingKey>,
         callback: Box<dyn FnOnce() + Send + 'static>,
-    ) -> Option<u32> {
-        self.bind(binding_keys, BindingCallback::Once(callback))
+    ) -> Result<BindingHandle, Error> {
+        self.bind_once_with(binding_keys, BindingOptions::default(), callback)
     }
 
     pub fn bind_multi(
         &mut self,
         binding_keys: Vec<BindingKey>,
         callback: Box<dyn FnMut()>,
-    ) -> Option<u32> {
-        self.bind(binding_keys, BindingCallback::Multi(callback))
+    ) -> Result<BindingHandle, Error> {
+        self.bind_multi_with(binding_keys, BindingOptions::default(), callback)
+    }
+
+    pub fn bind_once_with(
+        &mut self,
+        binding_keys: Vec<BindingKey>,
+        options: BindingOptions,
+        callback: Box<dyn FnOnce() + Send + 'static>,
+    ) -> Result<BindingHandle, Error> {
+        self.bind(binding_keys, options, BindingCallback::Once(callback))
+    }
+
+    pub fn bind_multi_with(
+        &mut self,
+        binding_keys: Vec<BindingKey>,
+        options: BindingOptions,
+        callback: Box<dyn FnMut()>,
+    ) -> Result<BindingHandle, Error> {
+        self.bind(binding_keys, options, BindingCallback::Multi(callback))
+    }
+
+    /// Binds `key` as a dual-role key with the default `DualRole::tapping_term`:
+    /// `callback` is called when it is tapped alone, and it acts as `hold_as`
+    /// when held together with other keys.
+    ///
+    /// Acting as `hold_as` only affects the bindings of inbot. Other applications
+    /// still see `key` itself and never `hold_as`, e.g. CapsLock still toggles caps
+    /// lock. To hide `key` from them, bind with `BindingOptions::consume` through
+    /// `bind_multi_with` instead.
+    pub fn bind_dual_role(
+        &mut self,
+        key: KeyCode,
+        hold_as: KeyCode,
+        callback: Box<dyn FnMut()>,
+    ) -> Result<BindingHandle, Error> {
+        let options = BindingOptions {
+            dual_role: Some(DualRole::new(hold_as)),
+            consume: false,
+            ..Default::default()
+        };
+        let binding_key = BindingKey {
+            key,
+            modifer_keys: Vec::new(),
+        };
+        self.bind_multi_with(vec![binding_key], options, callback)
     }
 
-    fn bind(&mut self, binding_keys: Vec<BindingKey>, callback: BindingCallback) -> Option<u32> {
-        let binding_info = BindingInfo::new(binding_keys, self.binding_notifier_tx.clone());
+    fn bind(
+        &mut self,
+        binding_keys: Vec<BindingKey>,
+        options: BindingOptions,
+        callback: BindingCallback,
+    ) -> Result<BindingHandle, Error> {
+        if options.consume && !self.can_consume {
+            return Err(Error::ConsumeUnsupported);
+        }
+        let binding_info =
+            BindingInfo::new(binding_keys, options, self.binding_notifier_tx.clone());
+        binding_info.validate()?;
         let uid = binding_info.get_uid();
-        if let Err(e) = self.binding_opt_tx.send(ListenerOpt::Bind(binding_info)) {
-            println!("subscribe event failed, {}", e);
-            return None;
+        let active = binding_info.get_active_flag();
+        self.send(ListenerOpt::Bind(binding_info))?;
+        let bound_callback = BoundCallback {
+            callback,
+            active: active.clone(),
+        };
+        self.callbacks.insert(uid, bound_callback);
+        Ok(BindingHandle::new(uid, active, self.binding_opt_tx.clone()))
+    }
+
+    /// Removes a binding of this proxy by uid, e.g. one whose handle was detached.
+    /// Its callback is never called again.
+    pub fn unbind(&mut self, binding_uid: u32) -> Result<(), Error> {
+        let bound_callback = self.callbacks.remove(&binding_uid);
+        if !bound_callback
+            .is_some_and(|bound_callback| bound_callback.active.swap(false, Ordering::SeqCst))
+        {
+            return Err(Error::UnknownBinding(binding_uid));
+        }
+        self.pending_uids.remove(&binding_uid);
+        self.send(ListenerOpt::Unbind(binding_uid))
+    }
+
+    /// Enables a binding of this proxy that was disabled.
+    pub fn enable(&mut self, binding_uid: u32) -> Result<(), Error> {
+        self.set_enabled(binding_uid, true)
+    }
+
+    /// Disables a binding of this proxy until it's enabled again. A sequence it was
+    /// in the middle of is dropped, and has to be typed from the first key again.
+    pub fn disable(&mut self, binding_uid: u32) -> Result<(), Error> {
+        self.set_enabled(binding_uid, false)
+    }
+
+    fn set_enabled(&mut self, binding_uid: u32, enabled: bool) -> Result<(), Error> {
+        if !self.callbacks.contains_key(&binding_uid) {
+            return Err(Error::UnknownBinding(binding_uid));
+        }
+        self.send(ListenerOpt::SetEnabled(binding_uid, enabled))
+    }
+
+    /// Enables every binding in `group`, see `BindingOptions::group`, including those
+    /// of other proxies.
+    pub fn enable_group(&mut self, group: &str) -> Result<(), Error> {
+        self.send(ListenerOpt::SetGroupEnabled(group.to_string(), true))
+    }
+
+    /// Disables every binding in `group`, see `BindingOptions::group`, including those
+    /// of other proxies.
+    pub fn disable_group(&mut self, group: &str) -> Result<(), Error> {
+        self.send(ListenerOpt::SetGroupEnabled(group.to_string(), false))
+    }
+
+    /// Stops every binding from matching, e.g. while a text field has focus, until
+    /// `resume`. Bindings keep whether they are enabled on their own.
+    pub fn pause(&mut self) -> Result<(), Error> {
+        self.send(ListenerOpt::SetPaused(true))
+    }
+
+    pub fn resume(&mut self) -> Result<(), Error> {
+        self.send(ListenerOpt::SetPaused(false))
+    }
+
+    /// Pushes `keymap` on top of the keymap stack, so that the bindings of it, see
+    /// `BindingOptions::keymap`, start matching. They shadow the bindings of the same
+    /// keys in the keymaps below, while other keys still match those below. Sequences
+    /// below that were waiting for a key the keymap binds are dropped.
+    pub fn push_keymap(&mut self, keymap: &str) -> Result<(), Error> {
+        self.send(ListenerOpt::PushKeymap(keymap.to_string()))
+    }
+
+    /// Removes the topmost `keymap` from the stack, whether or not it's on top, so that
+    /// closing a dialog doesn't pop a keymap pushed after it. Sequences its bindings
+    /// were in the middle of are dropped.
+    pub fn pop_keymap(&mut self, keymap: &str) -> Result<(), Error> {
+        self.send(ListenerOpt::PopKeymap(keymap.to_string()))
+    }
+
+    /// Sets the longest pause allowed between two keys of a sequence, for bindings
+    /// without their own `BindingOptions::sequence_timeout`. `None` never times out.
+    /// Defaults to `DEFAULT_SEQUENCE_TIMEOUT`.
+    pub fn set_default_sequence_timeout(&mut self, timeout: Option<Duration>) -> Result<(), Error> {
+        self.send(ListenerOpt::SetDefaultSequenceTimeout(timeout))
+    }
+
+    /// Sets how long a binding that is also the prefix of a longer binding, such as
+    /// `Ctrl+K` next to `Ctrl+K, Ctrl+C`, waits for the next key before triggering.
+    /// Defaults to `DEFAULT_AMBIGUOUS_TIMEOUT`.
+    pub fn set_ambiguous_timeout(&mut self, timeout: Duration) -> Result<(), Error> {
+        self.send(ListenerOpt::SetAmbiguousTimeout(timeout))
+    }
+
+    /// Remaps `from` to `to`, e.g. `Capslock -> ControlLeft` or `AltRight+J -> LeftArrow`.
+    ///
+    /// The events of `from` are swallowed and those of `to` injected instead, which
+    /// only works with backends that can consume input, and fails with
+    /// `Error::ConsumeUnsupported` on the others. Bindings see the remapped events.
+    /// Returns the uid to pass to `unremap`.
+    pub fn remap(&mut self, from: BindingKey, to: BindingKey) -> Result<u32, Error> {
+        if !self.can_consume {
+            return Err(Error::ConsumeUnsupported);
         }
-        self.callbacks.insert(uid, callback);
-        Some(uid)
+        let remap_info = RemapInfo::new(from, to);
+        let uid = remap_info.get_uid();
+        self.send(ListenerOpt::Remap(remap_info))?;
+        self.remap_uids.insert(uid);
+        Ok(uid)
     }
 
-    fn unbind(&mut self, binding_uid: u32) {
-        if let Err(e) = self.binding_opt_tx.send(ListenerOpt::Unbind(binding_uid)) {
-            println!("unbind:{} failed, {}", binding_uid, e);
+    /// Removes a remap of this proxy. Keys it still holds down are released.
+    pub fn unremap(&mut self, remap_uid: u32) -> Result<(), Error> {
+        if !self.remap_uids.remove(&remap_uid) {
+            return Err(Error::UnknownBinding(remap_uid));
         }
+        self.send(ListenerOpt::Unremap(remap_uid))
+    }
+
+    /// Subscribes to every input event accepted by `filter`, as reported by the
+    /// backend, before remapping and matching.
+    pub fn subscribe_raw(&mut self, filter: RawEventFilter) -> Result<RawSubscription, Error> {
+        let (subscription, subscriber) = RawSubscription::new(filter);
+        self.send(ListenerOpt::SubscribeRaw(subscriber))?;
+        Ok(subscription)
     }
 
-    pub fn update(&mut self) {
+    /// Whether a binding of this proxy has fully matched but waits for the next key,
+    /// as of the last `update`. UIs may show a "waiting for next key" state meanwhile.
+    pub fn is_waiting_for_next_key(&self) -> bool {
+        !self.pending_uids.is_empty()
+    }
+
+    /// The bindings that wait for the next key, as of the last `update`.
+    pub fn get_pending_uids(&self) -> Vec<u32> {
+        self.pending_uids.iter().copied().collect()
+    }
+
+    fn send(&self, listener_opt: ListenerOpt) -> Result<(), Error> {
+        self.binding_opt_tx
+            .send(listener_opt)
+            .map_err(|_| Error::ListenerNotRunning)
+    }
+
+    /// Calls the callbacks of the bindings triggered since the last update.
+    ///
+    /// Bindings that the listener rejected, such as a second dual-role binding of the
+    /// same key, are dropped, and the first rejection is returned once every callback
+    /// has been called.
+    pub fn update(&mut self) -> Result<(), Error> {
+        self.update_with(|_| {})
+    }
+
+    /// same as `update`, and reports the uid of every binding whose callback was called
+    pub(crate) fn update_with(&mut self, mut on_triggered: impl FnMut(u32)) -> Result<(), Error> {
+        let mut result = Ok(());
         loop {
             match self.binding_notifier_rx.try_recv() {
-                Ok(uid) => self.trigger_callback(uid),
-                Err(TryRecvError::Empty) => break,
-                Err(TryRecvError::Disconnected) => {
-                    println!("disconnected");
+                Ok(BindingNotice::Triggered(uid)) => {
+                    self.pending_uids.remove(&uid);
+                    if self.trigger_callback(uid) {
+                        on_triggered(uid);
+                    }
+                }
+                Ok(BindingNotice::Pending(uid)) => {
+                    self.pending_uids.insert(uid);
+                }
+                Ok(BindingNotice::Cancelled(uid)) | Ok(BindingNotice::Resolved(uid)) => {
+                    self.pending_uids.remove(&uid);
+                }
+                Ok(BindingNotice::Unbound(uid)) => {
+                    self.pending_uids.remove(&uid);
+                    self.callbacks.remove(&uid);
                 }
+                Ok(BindingNotice::Rejected(uid)) => {
+                    self.callbacks.remove(&uid);
+                    if result.is_ok() {
+                        result = Err(Error::DuplicateBinding(uid));
+                    }
+                }
+                // the proxy holds a sender itself, so it never disconnects
+                Err(_) => break,
             }
         }
+        result
     }
 
-    fn trigger_callback(&mut self, uid: u32) {
-        match self.callbacks.remove(&uid) {
-            Some(BindingCallback::Once(callback)) => {
-                self.unbind(uid);
+    fn trigger_callback(&mut self, uid: u32) -> bool {
+        let bound_callback = match self.callbacks.remove(&uid) {
+            Some(bound_callback) => bound_callback,
+            None => return false,
+        };
+        // unbound through its handle, before the listener knew
+        if !bound_callback.active.load(Ordering::SeqCst) {
+            return false;
+        }
+        match bound_callback.callback {
+            BindingCallback::Once(callback) => {
+                bound_callback.active.store(false, Ordering::SeqCst);
+                // the listener is gone if this fails, so the binding is gone too
+                let _ = self.send(ListenerOpt::Unbind(uid));
                 callback();
             }
-            Some(BindingCallback::Multi(mut callback)) => {
+            BindingCallback::Multi(mut callback) => {
                 callback();
-                self.callbacks.insert(uid, BindingCallback::Multi(callback));
+                let bound_callback = BoundCallback {
+                    callback: BindingCallback::Multi(callback),
+                    active: bound_callback.active,
+                };
+                self.callbacks.insert(uid, bound_callback);
             }
-            _ => {}
         }
+        true
     }
 }
 
-struct Listener {
+pub(crate) struct Listener {
+    backend: Box<dyn InputBackend>,
+    raw_event_hub: RawEventHub,
+    remapper: Remapper,
     binding_key_mgr: BindingKeyMgr,
     binding_opt_rx: Receiver<ListenerOpt>,
 }
 
 impl Listener {
-    fn new(binding_opt_rx: Receiver<ListenerOpt>) -> Self {
+    pub(crate) fn new(
+        backend: Box<dyn InputBackend>,
+        binding_opt_rx: Receiver<ListenerOpt>,
+    ) -> Self {
         Self {
+            backend,
+            raw_event_hub: RawEventHub::new(),
+            remapper: Remapper::new(),
             binding_key_mgr: BindingKeyMgr::new(),
             binding_opt_rx,
         }
     }
 
-    unsafe fn thread_loop(&mut self) {
-        KEYBOARD_HOOK = SetWindowsHookExW(
-            WH_KEYBOARD_LL,
-            Some(keyboard_callback),
-            HMODULE::default(),
-            0,
-        )
-        .unwrap();
-        MOUSE_HOOK =
-            SetWindowsHookExW(WH_MOUSE_LL, Some(mouse_callback), HMODULE::default(), 0).unwrap();
-        let mut msg = MSG::default();
-        while self.handle_event_opt() {
-            match PeekMessageW(&mut msg, None, 0, 0, PM_REMOVE).0 {
-                -1 => {
-                    println!("get message failed");
-                    return;
-                }
-                0 => std::thread::sleep(std::time::Duration::from_millis(1)),
-                _ => (),
+    /// runs until stopped, the backend must be installed
+    fn thread_loop(&mut self) -> Result<(), Error> {
+        let mut result = self.run();
+        // don't leave remapped keys stuck down
+        let input_keys = self.remapper.release_all(self.backend.now());
+        let released = self.inject(&input_keys);
+        self.backend.uninstall();
+        if result.is_ok() {
+            result = released;
+        }
+        if let Err(e) = &result {
+            diag!(error, "listener stopped, {}", e);
+        }
+        result.map_err(Error::from)
+    }
+
+    fn run(&mut self) -> io::Result<()> {
+        while self.handle_event_opt()? {
+            self.poll_backend()?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn get_binding_key_mgr(&self) -> &BindingKeyMgr {
+        &self.binding_key_mgr
+    }
+
+    pub(crate) fn install_backend(&mut self) -> io::Result<()> {
+        self.backend.install()
+    }
+
+    pub(crate) fn poll_backend(&mut self) -> io::Result<()> {
+        let raw_event_hub = &mut self.raw_event_hub;
+        let remapper = &mut self.remapper;
+        let binding_key_mgr = &mut self.binding_key_mgr;
+        self.backend.poll(&mut |input_key| {
+            let _span = enter_span!("input_key", key = ?input_key.key, opt = ?input_key.opt);
+            raw_event_hub.on_input_key(input_key);
+            let input_keys = match remapper.on_input_key(input_key) {
+                Some(input_keys) => input_keys,
+                None => return binding_key_mgr.on_input_key(input_key),
             };
+            let mut injected_keys = Vec::new();
+            for input_key in input_keys {
+                match binding_key_mgr.on_input_key(input_key) {
+                    InputDecision::Pass => injected_keys.push(input_key),
+                    InputDecision::Consume => (),
+                    InputDecision::Replace(mut input_keys) => injected_keys.append(&mut input_keys),
+                }
+            }
+            if injected_keys.is_empty() {
+                return InputDecision::Consume;
+            }
+            InputDecision::Replace(injected_keys)
+        })?;
+        self.binding_key_mgr.on_tick(self.backend.now());
+        let replay_keys = self.binding_key_mgr.take_replay_keys();
+        if !replay_keys.is_empty() {
+            self.backend.replay(&replay_keys)?;
+        }
+        Ok(())
+    }
+
+    /// injects events that didn't come from the backend, letting the bindings see them too
+    fn inject(&mut self, input_keys: &[InputKey]) -> io::Result<()> {
+        if input_keys.is_empty() {
+            return Ok(());
         }
-        let _ = UnhookWindowsHookEx(KEYBOARD_HOOK);
-        let _ = UnhookWindowsHookEx(MOUSE_HOOK);
+        for input_key in input_keys {
+            let _ = self.binding_key_mgr.on_input_key(*input_key);
+        }
+        self.backend.replay(input_keys)
     }
 
-    fn handle_event_opt(&mut self) -> bool {
+    /// returns `false` once the listener should stop
+    pub(crate) fn handle_event_opt(&mut self) -> io::Result<bool> {
         loop {
             match self.binding_opt_rx.try_recv() {
                 Ok(ListenerOpt::Bind(binding_info)) => {
-                    self.binding_key_mgr.bind(binding_info);
+                    // the proxy is told through `BindingNotice::Rejected`
+                    let _ = self.binding_key_mgr.bind(binding_info);
                 }
                 Ok(ListenerOpt::Unbind(uid)) => {
                     self.binding_key_mgr.unbind(uid);
                 }
+                Ok(ListenerOpt::SetEnabled(uid, enabled)) => {
+                    self.binding_key_mgr.set_enabled(uid, enabled);
+                }
+                Ok(ListenerOpt::SetGroupEnabled(group, enabled)) => {
+                    self.binding_key_mgr.set_group_enabled(&group, enabled);
+                }
+                Ok(ListenerOpt::SetPaused(paused)) => {
+                    self.binding_key_mgr.set_paused(paused);
+                }
+                Ok(ListenerOpt::PushKeymap(keymap)) => {
+                    self.binding_key_mgr.push_keymap(keymap);
+                }
+                Ok(ListenerOpt::PopKeymap(keymap)) => {
+                    self.binding_key_mgr.pop_keymap(&keymap);
+                }
+                Ok(ListenerOpt::SetDefaultSequenceTimeout(timeout)) => {
+                    self.binding_key_mgr.set_default_sequence_timeout(timeout);
+                }
+                Ok(ListenerOpt::SetAmbiguousTimeout(timeout)) => {
+                    self.binding_key_mgr.set_ambiguous_timeout(timeout);
+                }
+                Ok(ListenerOpt::Remap(remap_info)) => {
+                    self.remapper.remap(remap_info);
+                }
+                Ok(ListenerOpt::Unremap(uid)) => {
+                    let input_keys = self.remapper.unremap(uid, self.backend.now());
+                    self.inject(&input_keys)?;
+                }
+                Ok(ListenerOpt::SubscribeRaw(subscriber)) => {
+                    self.raw_event_hub.subscribe(subscriber);
+                }
                 Ok(ListenerOpt::StopListen) => {
-                    return false;
+                    return Ok(false);
                 }
                 Err(TryRecvError::Empty) => break,
-                Err(TryRecvError::Disconnected) => {
-                    println!("try receive EventOpt failed, channel disconnected");
-                    return false;
-                }
+                // every proxy is gone
+                Err(TryRecvError::Disconnected) => return Ok(false),
             };
         }
-        return true;
+        Ok(true)
     }
 }
-
-extern "system" fn keyboard_callback(ncode: i32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
-    unsafe { device_event_callback(KEYBOARD_HOOK, ncode, wparam, lparam) }
-}
-
-extern "system" fn mouse_callback(ncode: i32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
-    unsafe { device_event_callback(MOUSE_HOOK, ncode, wparam, lparam) }
-}
-
-unsafe fn device_event_callback(
-    hook: HHOOK,
-    ncode: i32,
-    wparam: WPARAM,
-    lparam: LPARAM,
-) -> LRESULT {
-    if (ncode == HC_ACTION as i32) && (wparam.0 as u32 != WM_MOUSEMOVE) {
-        let listener = LISTENER.get_mut().unwrap();
-        if let Some(input_key) = InputKey::from(wparam, lparam) {
-            listener.binding_key_mgr.on_input_key(input_key);
-        } else {
-            println!("parse event:{} failed", wparam.0);
-        }
-    }
-    CallNextHookEx(hook, ncode, wparam, lparam)
-}
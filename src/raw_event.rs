@@ -0,0 +1,196 @@
+use crate::virtual_key::{InputKey, KeyCode, KeyOpt};
+use std::collections::{HashSet, VecDeque};
+use std::sync::{Arc, Condvar, Mutex, Weak};
+use std::time::Duration;
+
+/// The buffer size of `RawEventFilter::default()`.
+pub const DEFAULT_RAW_EVENT_CAPACITY: usize = 1024;
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum DeviceKind {
+    Keyboard,
+    Mouse,
+}
+
+/// An input event as the backend reported it, before remapping and matching.
+#[derive(PartialEq, Clone, Debug)]
+pub struct RawEvent {
+    /// carries the timestamp, see `InputKey::time`
+    pub input_key: InputKey,
+    pub device: DeviceKind,
+    /// the modifers held when the event happened, not counting `input_key` itself
+    pub modifer_keys: Vec<KeyCode>,
+}
+
+/// Which raw events a subscription gets, and how many it buffers.
+#[derive(Clone, Debug)]
+pub struct RawEventFilter {
+    /// only these keys, or every key when `None`
+    pub keys: Option<HashSet<KeyCode>>,
+    /// only these kinds of event, or every kind when `None`
+    pub opts: Option<Vec<KeyOpt>>,
+    /// when the buffer is full, the oldest event is dropped
+    pub capacity: usize,
+}
+
+impl Default for RawEventFilter {
+    fn default() -> Self {
+        Self {
+            keys: None,
+            opts: None,
+            capacity: DEFAULT_RAW_EVENT_CAPACITY,
+        }
+    }
+}
+
+impl RawEventFilter {
+    fn accepts(&self, input_key: &InputKey) -> bool {
+        if let Some(keys) = &self.keys {
+            if !keys.contains(&input_key.key) {
+                return false;
+            }
+        }
+        if let Some(opts) = &self.opts {
+            if !opts.contains(&input_key.opt) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+#[derive(Default)]
+struct RawEventBuffer {
+    events: VecDeque<RawEvent>,
+    dropped_count: usize,
+}
+
+struct RawEventQueue {
+    buffer: Mutex<RawEventBuffer>,
+    not_empty: Condvar,
+}
+
+/// The receiving end of `ListenerProxy::subscribe_raw`.
+///
+/// The listener never waits on a subscription: when the buffer is full, the
+/// oldest event is dropped and counted. Dropping the subscription unsubscribes.
+pub struct RawSubscription {
+    queue: Arc<RawEventQueue>,
+}
+
+impl RawSubscription {
+    pub(crate) fn new(filter: RawEventFilter) -> (Self, RawSubscriber) {
+        let queue = Arc::new(RawEventQueue {
+            buffer: Mutex::new(RawEventBuffer::default()),
+            not_empty: Condvar::new(),
+        });
+        let subscriber = RawSubscriber {
+            filter,
+            queue: Arc::downgrade(&queue),
+        };
+        (Self { queue }, subscriber)
+    }
+
+    pub fn try_recv(&self) -> Option<RawEvent> {
+        self.queue.buffer.lock().unwrap().events.pop_front()
+    }
+
+    /// waits up to `timeout` for an event
+    pub fn recv_timeout(&self, timeout: Duration) -> Option<RawEvent> {
+        let buffer = self.queue.buffer.lock().unwrap();
+        let (mut buffer, _) = self
+            .queue
+            .not_empty
+            .wait_timeout_while(buffer, timeout, |buffer| buffer.events.is_empty())
+            .unwrap();
+        buffer.events.pop_front()
+    }
+
+    /// takes every buffered event
+    pub fn drain(&self) -> Vec<RawEvent> {
+        self.queue.buffer.lock().unwrap().events.drain(..).collect()
+    }
+
+    /// the number of events dropped so far because the buffer was full
+    pub fn get_dropped_count(&self) -> usize {
+        self.queue.buffer.lock().unwrap().dropped_count
+    }
+}
+
+/// The listener's end of a `RawSubscription`.
+pub(crate) struct RawSubscriber {
+    filter: RawEventFilter,
+    queue: Weak<RawEventQueue>,
+}
+
+/// Hands every input event to the raw subscriptions.
+pub(crate) struct RawEventHub {
+    subscribers: Vec<RawSubscriber>,
+    holding_modifer_keys: Vec<KeyCode>,
+}
+
+impl RawEventHub {
+    pub fn new() -> Self {
+        Self {
+            subscribers: Vec::new(),
+            holding_modifer_keys: Vec::new(),
+        }
+    }
+
+    pub fn subscribe(&mut self, subscriber: RawSubscriber) {
+        self.subscribers.push(subscriber);
+    }
+
+    pub fn on_input_key(&mut self, input_key: InputKey) {
+        if !self.subscribers.is_empty() {
+            let raw_event = RawEvent {
+                input_key,
+                device: if input_key.is_mouse_event() {
+                    DeviceKind::Mouse
+                } else {
+                    DeviceKind::Keyboard
+                },
+                modifer_keys: self
+                    .holding_modifer_keys
+                    .iter()
+                    .copied()
+                    .filter(|key| *key != input_key.key)
+                    .collect(),
+            };
+            // dropped subscriptions are forgotten
+            self.subscribers
+                .retain(|subscriber| match subscriber.queue.upgrade() {
+                    Some(queue) => {
+                        if subscriber.filter.accepts(&input_key) {
+                            push_drop_oldest(&queue, &subscriber.filter, raw_event.clone());
+                        }
+                        true
+                    }
+                    None => false,
+                });
+        }
+        if input_key.key.is_modifier() {
+            if input_key.opt == KeyOpt::Down && !self.holding_modifer_keys.contains(&input_key.key)
+            {
+                self.holding_modifer_keys.push(input_key.key);
+            } else if input_key.opt == KeyOpt::Up {
+                self.holding_modifer_keys
+                    .retain(|key| *key != input_key.key);
+            }
+        }
+    }
+}
+
+fn push_drop_oldest(queue: &RawEventQueue, filter: &RawEventFilter, raw_event: RawEvent) {
+    let mut buffer = queue.buffer.lock().unwrap();
+    if filter.capacity == 0 {
+        buffer.dropped_count += 1;
+        return;
+    }
+    while buffer.events.len() >= filter.capacity {
+        buffer.events.pop_front();
+        buffer.dropped_count += 1;
+    }
+    buffer.events.push_back(raw_event);
+    queue.not_empty.notify_one();
+}
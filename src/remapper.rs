@@ -0,0 +1,203 @@
+use crate::binding_key_mgr::BindingKey;
+use crate::virtual_key::{InputKey, KeyCode, KeyOpt};
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicU32, Ordering};
+
+/// A remap of `from` to `to`, e.g. `Capslock -> ControlLeft` or `AltRight+J -> LeftArrow`.
+pub(crate) struct RemapInfo {
+    remap_uid: u32,
+    from: BindingKey,
+    to: BindingKey,
+}
+
+impl RemapInfo {
+    pub fn new(from: BindingKey, to: BindingKey) -> Self {
+        static LAST_ALLOCATED_REMAP_UID: AtomicU32 = AtomicU32::new(0);
+        let uid = LAST_ALLOCATED_REMAP_UID.fetch_add(1, Ordering::SeqCst) + 1;
+        Self {
+            remap_uid: uid,
+            from,
+            to,
+        }
+    }
+
+    pub fn get_uid(&self) -> u32 {
+        self.remap_uid
+    }
+}
+
+/// A source key that is held down and remapped.
+struct ActiveRemap {
+    remap_uid: u32,
+    to_key: KeyCode,
+    pressed_modifer_keys: Vec<KeyCode>, // modifers of `to` that weren't held already
+    released_modifer_keys: Vec<KeyCode>, // modifers of `from`, released while remapped
+}
+
+/// Turns the physical input events into the events other applications should see.
+///
+/// While a remapped key is held, the modifers of `from` are released and those of
+/// `to` pressed, so `AltRight+J -> LeftArrow` gives a plain `LeftArrow`. Everything is
+/// put back when the key is released: the remap releases exactly what it pressed,
+/// auto-repeat repeats the target key only, and a modifer released meanwhile doesn't
+/// get released twice.
+pub(crate) struct Remapper {
+    remaps: Vec<RemapInfo>,
+    active_remaps: HashMap<KeyCode, ActiveRemap>,
+    holding_keys: HashSet<KeyCode>,
+    released_keys: HashSet<KeyCode>, // held, but released by an active remap
+}
+
+impl Remapper {
+    pub fn new() -> Self {
+        Self {
+            remaps: Vec::new(),
+            active_remaps: HashMap::new(),
+            holding_keys: HashSet::new(),
+            released_keys: HashSet::new(),
+        }
+    }
+
+    pub fn remap(&mut self, remap_info: RemapInfo) {
+        self.remaps.push(remap_info);
+    }
+
+    /// removes a remap, and returns the events that release whatever it still holds
+    pub fn unremap(&mut self, uid: u32, time: u32) -> Vec<InputKey> {
+        self.remaps.retain(|remap_info| remap_info.remap_uid != uid);
+        let keys: Vec<KeyCode> = self
+            .active_remaps
+            .iter()
+            .filter(|(_, active_remap)| active_remap.remap_uid == uid)
+            .map(|(key, _)| *key)
+            .collect();
+        let mut input_keys = Vec::new();
+        for key in keys {
+            input_keys.append(&mut self.release(key, time));
+        }
+        input_keys
+    }
+
+    /// returns `None` when `input_key` isn't remapped, else the events to emit instead
+    pub fn on_input_key(&mut self, input_key: InputKey) -> Option<Vec<InputKey>> {
+        if input_key.opt == KeyOpt::Up {
+            self.holding_keys.remove(&input_key.key);
+            if self.active_remaps.contains_key(&input_key.key) {
+                return Some(self.release(input_key.key, input_key.time));
+            }
+            if self.released_keys.remove(&input_key.key) {
+                // already released for the other applications
+                return Some(Vec::new());
+            }
+            return None;
+        }
+        if input_key.opt != KeyOpt::Down {
+            return None;
+        }
+        if let Some(active_remap) = self.active_remaps.get(&input_key.key) {
+            // auto-repeat
+            return Some(vec![InputKey::new(
+                active_remap.to_key,
+                KeyOpt::Down,
+                input_key.time,
+            )]);
+        }
+        let remap_info = self.find_remap(input_key.key).map(|remap_info| {
+            (
+                remap_info.remap_uid,
+                remap_info.from.clone(),
+                remap_info.to.clone(),
+            )
+        });
+        self.holding_keys.insert(input_key.key);
+        let (remap_uid, from, to) = remap_info?;
+        let time = input_key.time;
+        let mut input_keys = Vec::new();
+        let mut released_modifer_keys = Vec::new();
+        for modifer_key in &from.modifer_keys {
+            for holding_key in &self.holding_keys {
+                if modifer_key.matches(*holding_key) && !self.released_keys.contains(holding_key) {
+                    released_modifer_keys.push(*holding_key);
+                }
+            }
+        }
+        for modifer_key in &released_modifer_keys {
+            self.released_keys.insert(*modifer_key);
+            input_keys.push(InputKey::new(*modifer_key, KeyOpt::Up, time));
+        }
+        let mut pressed_modifer_keys = Vec::new();
+        for modifer_key in &to.modifer_keys {
+            let is_held = self.holding_keys.iter().any(|holding_key| {
+                modifer_key.matches(*holding_key) && !self.released_keys.contains(holding_key)
+            });
+            if !is_held {
+                let modifer_key = to_physical_key(*modifer_key);
+                pressed_modifer_keys.push(modifer_key);
+                input_keys.push(InputKey::new(modifer_key, KeyOpt::Down, time));
+            }
+        }
+        let to_key = to_physical_key(to.key);
+        input_keys.push(InputKey::new(to_key, KeyOpt::Down, time));
+        self.active_remaps.insert(
+            input_key.key,
+            ActiveRemap {
+                remap_uid,
+                to_key,
+                pressed_modifer_keys,
+                released_modifer_keys,
+            },
+        );
+        Some(input_keys)
+    }
+
+    /// the remap of `key` with the most modifers, all of which are held
+    fn find_remap(&self, key: KeyCode) -> Option<&RemapInfo> {
+        self.remaps
+            .iter()
+            .filter(|remap_info| remap_info.from.key.matches(key))
+            .filter(|remap_info| {
+                remap_info.from.modifer_keys.iter().all(|modifer_key| {
+                    self.holding_keys
+                        .iter()
+                        .any(|holding_key| modifer_key.matches(*holding_key))
+                })
+            })
+            .max_by_key(|remap_info| remap_info.from.modifer_keys.len())
+    }
+
+    fn release(&mut self, key: KeyCode, time: u32) -> Vec<InputKey> {
+        let active_remap = match self.active_remaps.remove(&key) {
+            Some(active_remap) => active_remap,
+            None => return Vec::new(),
+        };
+        let mut input_keys = vec![InputKey::new(active_remap.to_key, KeyOpt::Up, time)];
+        for modifer_key in active_remap.pressed_modifer_keys.iter().rev() {
+            input_keys.push(InputKey::new(*modifer_key, KeyOpt::Up, time));
+        }
+        // put back the modifers that are still held
+        for modifer_key in active_remap.released_modifer_keys {
+            if self.released_keys.remove(&modifer_key) {
+                input_keys.push(InputKey::new(modifer_key, KeyOpt::Down, time));
+            }
+        }
+        input_keys
+    }
+
+    /// returns the events that release every active remap
+    pub fn release_all(&mut self, time: u32) -> Vec<InputKey> {
+        let keys: Vec<KeyCode> = self.active_remaps.keys().copied().collect();
+        let mut input_keys = Vec::new();
+        for key in keys {
+            input_keys.append(&mut self.release(key, time));
+        }
+        input_keys
+    }
+}
+
+/// either-side keys are emitted as their left side
+fn to_physical_key(key: KeyCode) -> KeyCode {
+    match key.get_sides() {
+        Some((left, _)) => left,
+        None => key,
+    }
+}
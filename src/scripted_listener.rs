@@ -0,0 +1,114 @@
+use crate::backend::{Script, ScriptedBackend, ScriptedInput};
+use crate::error::Error;
+use crate::listener::{Listener, ListenerProxy};
+use crate::virtual_key::InputKey;
+use std::collections::HashMap;
+use std::sync::mpsc::channel;
+
+/// Runs a listener on the calling thread, fed by a `ScriptedBackend` instead of OS hooks.
+///
+/// Bind through `proxy()` as with `start_listen`, then `play` scripts. Callbacks are
+/// called through `ListenerProxy::update` at the end of every `play`, and the number
+/// of times each binding triggered is recorded for the assertions. Errors along the
+/// way are kept for `take_errors`.
+pub struct ScriptedListener {
+    listener: Listener,
+    proxy: ListenerProxy,
+    input: ScriptedInput,
+    triggered_counts: HashMap<u32, usize>,
+    errors: Vec<Error>,
+}
+
+impl ScriptedListener {
+    pub fn new() -> Self {
+        let backend = ScriptedBackend::new();
+        let input = backend.get_input();
+        let (listener_opt_tx, listener_opt_rx) = channel();
+        let mut listener = Listener::new(Box::new(backend), listener_opt_rx);
+        let _ = listener.install_backend();
+        Self {
+            listener,
+            proxy: ListenerProxy::new(listener_opt_tx, true),
+            input,
+            triggered_counts: HashMap::new(),
+            errors: Vec::new(),
+        }
+    }
+
+    pub fn proxy(&mut self) -> &mut ListenerProxy {
+        &mut self.proxy
+    }
+
+    pub fn play(&mut self, script: &Script) {
+        if let Err(e) = self.listener.handle_event_opt() {
+            self.errors.push(Error::from(e));
+        }
+        self.input.feed(script);
+        if let Err(e) = self.listener.poll_backend() {
+            self.errors.push(Error::from(e));
+        }
+        let triggered_counts = &mut self.triggered_counts;
+        if let Err(e) = self
+            .proxy
+            .update_with(|uid| *triggered_counts.entry(uid).or_default() += 1)
+        {
+            self.errors.push(e);
+        }
+    }
+
+    /// takes the errors of every `play` so far
+    pub fn take_errors(&mut self) -> Vec<Error> {
+        std::mem::take(&mut self.errors)
+    }
+
+    /// takes the events other applications would have seen so far, see `ScriptedInput::take_passed`
+    pub fn take_passed_keys(&mut self) -> Vec<InputKey> {
+        self.input.take_passed()
+    }
+
+    pub fn get_triggered_count(&self, uid: u32) -> usize {
+        self.triggered_counts.get(&uid).copied().unwrap_or(0)
+    }
+
+    /// the number of bindings the listener holds, see `ListenerProxy::unbind`
+    pub fn get_binding_count(&self) -> usize {
+        self.listener.get_binding_key_mgr().get_binding_count()
+    }
+
+    /// panics unless the listener holds no binding, nor any matching state of one
+    #[track_caller]
+    pub fn assert_no_bindings(&self) {
+        let binding_key_mgr = self.listener.get_binding_key_mgr();
+        assert!(
+            binding_key_mgr.is_empty(),
+            "the listener still holds {} bindings, or their matching state",
+            binding_key_mgr.get_binding_count()
+        );
+    }
+
+    pub fn clear_triggered_counts(&mut self) {
+        self.triggered_counts.clear();
+    }
+
+    /// panics unless binding `uid` has triggered exactly `times` times since the last clear
+    #[track_caller]
+    pub fn assert_triggered(&self, uid: u32, times: usize) {
+        let triggered = self.get_triggered_count(uid);
+        assert_eq!(
+            triggered, times,
+            "binding uid:{} triggered {} times, expected {}",
+            uid, triggered, times
+        );
+    }
+
+    #[track_caller]
+    pub fn assert_not_triggered(&self, uid: u32) {
+        self.assert_triggered(uid, 0);
+    }
+}
+
+impl Default for ScriptedListener {
+    fn default() -> Self {
+        Self::new()
+    }
+}
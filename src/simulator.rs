@@ -0,0 +1,94 @@
+use crate::backend::{SimulateBackend, SimulatedEvent};
+use crate::binding_key_mgr::BindingKey;
+use crate::error::Error;
+use crate::virtual_key::{CursorPos, KeyCode, KeyOpt};
+
+/// Sends synthetic keyboard and mouse input through a `SimulateBackend`.
+///
+/// Mouse buttons are pressed and released like any other `KeyCode`.
+pub struct Simulator {
+    backend: Box<dyn SimulateBackend>,
+}
+
+impl Simulator {
+    #[cfg(any(windows, target_os = "linux"))]
+    pub fn new() -> Result<Self, Error> {
+        Ok(Self::with_backend(
+            crate::backend::default_simulate_backend()?,
+        ))
+    }
+
+    pub fn with_backend(backend: Box<dyn SimulateBackend>) -> Self {
+        Self { backend }
+    }
+
+    pub fn press(&mut self, key: KeyCode) -> Result<(), Error> {
+        self.send(&[key_event(key, KeyOpt::Down)])
+    }
+
+    pub fn release(&mut self, key: KeyCode) -> Result<(), Error> {
+        self.send(&[key_event(key, KeyOpt::Up)])
+    }
+
+    pub fn tap(&mut self, key: KeyCode) -> Result<(), Error> {
+        self.send(&[key_event(key, KeyOpt::Down), key_event(key, KeyOpt::Up)])
+    }
+
+    /// presses the modifer keys in order, taps the key, then releases the modifer keys in reverse order
+    pub fn tap_binding_key(&mut self, binding_key: &BindingKey) -> Result<(), Error> {
+        let mut events = Vec::new();
+        push_binding_key_events(&mut events, binding_key);
+        self.send(&events)
+    }
+
+    /// types every binding key of a sequence one after another
+    pub fn tap_binding_keys(&mut self, binding_keys: &[BindingKey]) -> Result<(), Error> {
+        let mut events = Vec::new();
+        for binding_key in binding_keys {
+            push_binding_key_events(&mut events, binding_key);
+        }
+        self.send(&events)
+    }
+
+    pub fn move_to(&mut self, pos: CursorPos) -> Result<(), Error> {
+        self.send(&[SimulatedEvent::MouseMove(pos)])
+    }
+
+    /// taps a mouse button
+    pub fn click(&mut self, button: KeyCode) -> Result<(), Error> {
+        self.tap(button)
+    }
+
+    /// moves to `pos`, then taps a mouse button
+    pub fn click_at(&mut self, pos: CursorPos, button: KeyCode) -> Result<(), Error> {
+        self.send(&[
+            SimulatedEvent::MouseMove(pos),
+            key_event(button, KeyOpt::Down),
+            key_event(button, KeyOpt::Up),
+        ])
+    }
+
+    /// scrolls by wheel notches, positive is up for `delta_y` and right for `delta_x`
+    pub fn scroll(&mut self, delta_x: i32, delta_y: i32) -> Result<(), Error> {
+        self.send(&[SimulatedEvent::Scroll { delta_x, delta_y }])
+    }
+
+    fn send(&mut self, events: &[SimulatedEvent]) -> Result<(), Error> {
+        self.backend.send(events).map_err(Error::from)
+    }
+}
+
+fn key_event(key: KeyCode, opt: KeyOpt) -> SimulatedEvent {
+    SimulatedEvent::Key { key, opt }
+}
+
+fn push_binding_key_events(events: &mut Vec<SimulatedEvent>, binding_key: &BindingKey) {
+    for modifer_key in &binding_key.modifer_keys {
+        events.push(key_event(*modifer_key, KeyOpt::Down));
+    }
+    events.push(key_event(binding_key.key, KeyOpt::Down));
+    events.push(key_event(binding_key.key, KeyOpt::Up));
+    for modifer_key in binding_key.modifer_keys.iter().rev() {
+        events.push(key_event(*modifer_key, KeyOpt::Up));
+    }
+}
@@ -1,12 +1,17 @@
 use std::fmt::Display;
+#[cfg(windows)]
 use windows::Win32::Foundation::{LPARAM, POINT, WPARAM};
+#[cfg(windows)]
 use windows::Win32::UI::WindowsAndMessaging::{
-    GetCursorPos, KBDLLHOOKSTRUCT, MOUSEHOOKSTRUCT, WM_KEYDOWN, WM_KEYUP, WM_LBUTTONDBLCLK,
-    WM_LBUTTONDOWN, WM_LBUTTONUP, WM_MOUSEMOVE, WM_RBUTTONDBLCLK, WM_RBUTTONDOWN, WM_RBUTTONUP,
-    WM_SYSKEYDOWN,
+    GetCursorPos, KBDLLHOOKSTRUCT, LLKHF_EXTENDED, LLKHF_INJECTED, LLMHF_INJECTED, MSLLHOOKSTRUCT,
+    WM_KEYDOWN, WM_KEYUP, WM_LBUTTONDBLCLK, WM_LBUTTONDOWN, WM_LBUTTONUP, WM_MBUTTONDOWN,
+    WM_MBUTTONUP, WM_MOUSEHWHEEL, WM_MOUSEMOVE, WM_MOUSEWHEEL, WM_RBUTTONDBLCLK, WM_RBUTTONDOWN,
+    WM_RBUTTONUP, WM_SYSKEYDOWN, WM_SYSKEYUP, WM_XBUTTONDOWN, WM_XBUTTONUP, XBUTTON1, XBUTTON2,
 };
 
-#[derive(strum_macros::AsRefStr, PartialEq, Eq, Hash, Clone, Copy)]
+#[derive(
+    strum_macros::AsRefStr, strum_macros::EnumIter, PartialEq, Eq, Hash, Clone, Copy, Debug,
+)]
 pub enum KeyCode {
     Unknown(u32),
     Escape,
@@ -97,13 +102,144 @@ pub enum KeyCode {
     DownArrow,
     LeftArrow,
     RightArrow,
+    Numpad0,
+    Numpad1,
+    Numpad2,
+    Numpad3,
+    Numpad4,
+    Numpad5,
+    Numpad6,
+    Numpad7,
+    Numpad8,
+    Numpad9,
+    NumpadMultiply,
+    NumpadAdd,
+    /// THE NUMPAD COMMA OF SOME LAYOUTS
+    NumpadSeparator,
+    NumpadSubtract,
+    NumpadDecimal,
+    NumpadDivide,
+    /// REPORTED BY WINDOWS AS `Enter` WITH THE EXTENDED-KEY FLAG
+    NumpadEnter,
+    NumpadEqual,
+    NumLock,
+    F13,
+    F14,
+    F15,
+    F16,
+    F17,
+    F18,
+    F19,
+    F20,
+    F21,
+    F22,
+    F23,
+    F24,
+    /// ALSO KNOWN AS "APPS" AND "MENU"
+    ContextMenu,
+    Sleep,
+    Cancel,
+    Clear,
+    Select,
+    Print,
+    Execute,
+    Help,
+    VolumeMute,
+    VolumeDown,
+    VolumeUp,
+    MediaNextTrack,
+    MediaPrevTrack,
+    MediaStop,
+    MediaPlayPause,
+    LaunchMail,
+    LaunchMediaSelect,
+    LaunchApp1,
+    LaunchApp2,
+    BrowserBack,
+    BrowserForward,
+    BrowserRefresh,
+    BrowserStop,
+    BrowserSearch,
+    BrowserFavorites,
+    BrowserHome,
+    /// THE EXTRA KEY NEXT TO `ShiftLeft` ON ISO KEYBOARDS
+    IntlBackslash,
+    /// THE `\` KEY NEXT TO `ShiftRight` ON JIS AND ABNT KEYBOARDS
+    IntlRo,
+    /// ALSO KNOWN AS "HANGUL"
+    KanaMode,
+    Convert,
+    NonConvert,
+    /// ALSO KNOWN AS "KANJI"
+    Hanja,
+    Junja,
+    Final,
+    ImeOn,
+    ImeOff,
+    Accept,
+    ModeChange,
+    ProcessKey,
+    Oem8,
+    Attn,
+    CrSel,
+    ExSel,
+    EraseEof,
+    Play,
+    Zoom,
+    Pa1,
+    OemClear,
+    /// THE `AX` KEY OF JAPANESE AX KEYBOARDS, WINDOWS ONLY
+    OemAx,
+    /// THE NUMPAD `.` OF ABNT KEYBOARDS, WINDOWS ONLY
+    AbntC2,
+    /// THE ICO KEYBOARD KEYS, WINDOWS ONLY
+    IcoHelp,
+    Ico00,
+    IcoClear,
+    /// THE NOKIA/ERICSSON AND FUJITSU/OLIVETTI OEM KEYS, WINDOWS ONLY
+    OemReset,
+    OemJump,
+    OemPa1,
+    OemPa2,
+    OemPa3,
+    OemWsCtrl,
+    OemCuSel,
+    OemAttn,
+    OemFinish,
+    OemCopy,
+    OemAuto,
+    OemEnlw,
+    OemBackTab,
     MouseLeft,
     MouseRight,
     MouseMiddle,
+    /// THE SIDE BUTTON ALSO KNOWN AS "BACK"
+    MouseX1,
+    /// THE SIDE BUTTON ALSO KNOWN AS "FORWARD"
+    MouseX2,
+    /// A WHEEL NOTCH, REPORTED AS A `Down` IMMEDIATELY FOLLOWED BY AN `Up`
+    WheelUp,
+    /// A WHEEL NOTCH, REPORTED AS A `Down` IMMEDIATELY FOLLOWED BY AN `Up`
+    WheelDown,
+    /// A WHEEL NOTCH, REPORTED AS A `Down` IMMEDIATELY FOLLOWED BY AN `Up`
+    WheelLeft,
+    /// A WHEEL NOTCH, REPORTED AS A `Down` IMMEDIATELY FOLLOWED BY AN `Up`
+    WheelRight,
+    /// EITHER `ControlLeft` OR `ControlRight`, NEVER REPORTED BY A BACKEND
+    Control,
+    /// EITHER `ShiftLeft` OR `ShiftRight`, NEVER REPORTED BY A BACKEND
+    Shift,
+    /// EITHER `AltLeft` OR `AltRight`, NEVER REPORTED BY A BACKEND
+    Alt,
+    /// EITHER `MetaLeft` OR `MetaRight`, NEVER REPORTED BY A BACKEND
+    Meta,
 }
 
 macro_rules! create_converter {
-    ($from_func_name:ident, $to_func_name:ident, $($key:ident, $id:literal),+) => {
+    (
+        $from_func_name:ident, $to_func_name:ident, $($key:ident, $id:literal),+
+        $(; $($alias_key:ident => $alias_of:ident),+)?
+    ) => {
         pub fn $from_func_name(id: u32) -> KeyCode {
             match id {
                 $($id => KeyCode::$key,)+
@@ -111,10 +247,18 @@ macro_rules! create_converter {
             }
         }
 
+        /// either-side keys convert as their left side, aliases as the key they alias,
+        /// and keys missing from the table, such as wheel keys, as 0
         pub fn $to_func_name(&self) -> u32 {
             match self {
                 $(KeyCode::$key => $id,)+
+                $($(KeyCode::$alias_key => KeyCode::$alias_of.$to_func_name(),)+)?
+                KeyCode::Control => KeyCode::ControlLeft.$to_func_name(),
+                KeyCode::Shift => KeyCode::ShiftLeft.$to_func_name(),
+                KeyCode::Alt => KeyCode::AltLeft.$to_func_name(),
+                KeyCode::Meta => KeyCode::MetaLeft.$to_func_name(),
                 KeyCode::Unknown(v) => *v,
+                _ => 0,
             }
         }
 
@@ -122,7 +266,7 @@ macro_rules! create_converter {
 }
 
 impl KeyCode {
-    /// ref https://learn.microsoft.com/en-us/windows/win32/inputdev/virtual-key-codes
+    // ref https://learn.microsoft.com/en-us/windows/win32/inputdev/virtual-key-codes
     create_converter! {from_windows_id, to_windows_id,
         Escape, 0x1B,
         F1, 0x70,
@@ -210,14 +354,485 @@ impl KeyCode {
         DownArrow, 0x28,
         LeftArrow, 0x25,
         RightArrow, 0x27,
+        Numpad0, 0x60,
+        Numpad1, 0x61,
+        Numpad2, 0x62,
+        Numpad3, 0x63,
+        Numpad4, 0x64,
+        Numpad5, 0x65,
+        Numpad6, 0x66,
+        Numpad7, 0x67,
+        Numpad8, 0x68,
+        Numpad9, 0x69,
+        NumpadMultiply, 0x6A,
+        NumpadAdd, 0x6B,
+        NumpadSeparator, 0x6C,
+        NumpadSubtract, 0x6D,
+        NumpadDecimal, 0x6E,
+        NumpadDivide, 0x6F,
+        NumpadEqual, 0x92,
+        NumLock, 0x90,
+        F13, 0x7C,
+        F14, 0x7D,
+        F15, 0x7E,
+        F16, 0x7F,
+        F17, 0x80,
+        F18, 0x81,
+        F19, 0x82,
+        F20, 0x83,
+        F21, 0x84,
+        F22, 0x85,
+        F23, 0x86,
+        F24, 0x87,
+        ContextMenu, 0x5D,
+        Sleep, 0x5F,
+        Cancel, 0x3,
+        Clear, 0xC,
+        Select, 0x29,
+        Print, 0x2A,
+        Execute, 0x2B,
+        Help, 0x2F,
+        VolumeMute, 0xAD,
+        VolumeDown, 0xAE,
+        VolumeUp, 0xAF,
+        MediaNextTrack, 0xB0,
+        MediaPrevTrack, 0xB1,
+        MediaStop, 0xB2,
+        MediaPlayPause, 0xB3,
+        LaunchMail, 0xB4,
+        LaunchMediaSelect, 0xB5,
+        LaunchApp1, 0xB6,
+        LaunchApp2, 0xB7,
+        BrowserBack, 0xA6,
+        BrowserForward, 0xA7,
+        BrowserRefresh, 0xA8,
+        BrowserStop, 0xA9,
+        BrowserSearch, 0xAA,
+        BrowserFavorites, 0xAB,
+        BrowserHome, 0xAC,
+        IntlBackslash, 0xE2,
+        IntlRo, 0xC1,
+        KanaMode, 0x15,
+        Convert, 0x1C,
+        NonConvert, 0x1D,
+        Hanja, 0x19,
+        Junja, 0x17,
+        Final, 0x18,
+        ImeOn, 0x16,
+        ImeOff, 0x1A,
+        Accept, 0x1E,
+        ModeChange, 0x1F,
+        ProcessKey, 0xE5,
+        Oem8, 0xDF,
+        Attn, 0xF6,
+        CrSel, 0xF7,
+        ExSel, 0xF8,
+        EraseEof, 0xF9,
+        Play, 0xFA,
+        Zoom, 0xFB,
+        Pa1, 0xFD,
+        OemClear, 0xFE,
+        OemAx, 0xE1,
+        AbntC2, 0xC2,
+        IcoHelp, 0xE3,
+        Ico00, 0xE4,
+        IcoClear, 0xE6,
+        OemReset, 0xE9,
+        OemJump, 0xEA,
+        OemPa1, 0xEB,
+        OemPa2, 0xEC,
+        OemPa3, 0xED,
+        OemWsCtrl, 0xEE,
+        OemCuSel, 0xEF,
+        OemAttn, 0xF0,
+        OemFinish, 0xF1,
+        OemCopy, 0xF2,
+        OemAuto, 0xF3,
+        OemEnlw, 0xF4,
+        OemBackTab, 0xF5,
         MouseLeft, 0x01,
         MouseRight, 0x02,
-        MouseMiddle, 0x04
+        MouseMiddle, 0x04,
+        MouseX1, 0x05,
+        MouseX2, 0x06;
+        NumpadEnter => Enter
+    }
+
+    // ref https://github.com/torvalds/linux/blob/master/include/uapi/linux/input-event-codes.h
+    create_converter! {from_evdev_code, to_evdev_code,
+        Escape, 1,
+        F1, 59,
+        F2, 60,
+        F3, 61,
+        F4, 62,
+        F5, 63,
+        F6, 64,
+        F7, 65,
+        F8, 66,
+        F9, 67,
+        F10, 68,
+        F11, 87,
+        F12, 88,
+        Backquote, 41,
+        Num1, 2,
+        Num2, 3,
+        Num3, 4,
+        Num4, 5,
+        Num5, 6,
+        Num6, 7,
+        Num7, 8,
+        Num8, 9,
+        Num9, 10,
+        Num0, 11,
+        Minus, 12,
+        Equal, 13,
+        Backspace, 14,
+        Tab, 15,
+        KeyQ, 16,
+        KeyW, 17,
+        KeyE, 18,
+        KeyR, 19,
+        KeyT, 20,
+        KeyY, 21,
+        KeyU, 22,
+        KeyI, 23,
+        KeyO, 24,
+        KeyP, 25,
+        LeftBracket, 26,
+        RightBracket, 27,
+        Backslash, 43,
+        Capslock, 58,
+        KeyA, 30,
+        KeyS, 31,
+        KeyD, 32,
+        KeyF, 33,
+        KeyG, 34,
+        KeyH, 35,
+        KeyJ, 36,
+        KeyK, 37,
+        KeyL, 38,
+        Semicolon, 39,
+        Quote, 40,
+        Enter, 28,
+        ShiftLeft, 42,
+        KeyZ, 44,
+        KeyX, 45,
+        KeyC, 46,
+        KeyV, 47,
+        KeyB, 48,
+        KeyN, 49,
+        KeyM, 50,
+        Comma, 51,
+        Dot, 52,
+        Slash, 53,
+        ShiftRight, 54,
+        ControlLeft, 29,
+        MetaLeft, 125,
+        AltLeft, 56,
+        Space, 57,
+        AltRight, 100,
+        MetaRight, 126,
+        ControlRight, 97,
+        Printscreen, 99,
+        ScrollLock, 70,
+        Pause, 119,
+        Insert, 110,
+        Home, 102,
+        PageUp, 104,
+        Delete, 111,
+        End, 107,
+        PageDown, 109,
+        UpArrow, 103,
+        DownArrow, 108,
+        LeftArrow, 105,
+        RightArrow, 106,
+        Numpad0, 82,
+        Numpad1, 79,
+        Numpad2, 80,
+        Numpad3, 81,
+        Numpad4, 75,
+        Numpad5, 76,
+        Numpad6, 77,
+        Numpad7, 71,
+        Numpad8, 72,
+        Numpad9, 73,
+        NumpadMultiply, 55,
+        NumpadAdd, 78,
+        NumpadSeparator, 121,
+        NumpadSubtract, 74,
+        NumpadDecimal, 83,
+        NumpadDivide, 98,
+        NumpadEnter, 96,
+        NumpadEqual, 117,
+        NumLock, 69,
+        F13, 183,
+        F14, 184,
+        F15, 185,
+        F16, 186,
+        F17, 187,
+        F18, 188,
+        F19, 189,
+        F20, 190,
+        F21, 191,
+        F22, 192,
+        F23, 193,
+        F24, 194,
+        ContextMenu, 127,
+        Sleep, 142,
+        Cancel, 223,
+        Clear, 0x163,
+        Select, 0x161,
+        Print, 210,
+        Help, 138,
+        VolumeMute, 113,
+        VolumeDown, 114,
+        VolumeUp, 115,
+        MediaNextTrack, 163,
+        MediaPrevTrack, 165,
+        MediaStop, 166,
+        MediaPlayPause, 164,
+        LaunchMail, 155,
+        LaunchMediaSelect, 226,
+        LaunchApp1, 157,
+        LaunchApp2, 140,
+        BrowserBack, 158,
+        BrowserForward, 159,
+        BrowserRefresh, 173,
+        BrowserStop, 128,
+        BrowserSearch, 217,
+        BrowserFavorites, 156,
+        BrowserHome, 172,
+        IntlBackslash, 86,
+        IntlRo, 89,
+        KanaMode, 93,
+        Convert, 92,
+        NonConvert, 94,
+        Hanja, 123,
+        Play, 207,
+        Zoom, 0x174,
+        MouseLeft, 0x110,
+        MouseRight, 0x111,
+        MouseMiddle, 0x112,
+        MouseX1, 0x113,
+        MouseX2, 0x114
+    }
+
+    // set 1 scan codes of a US keyboard, extended keys prefixed with 0xE0,
+    // ref https://learn.microsoft.com/en-us/windows/win32/inputdev/about-keyboard-input#scan-codes
+    create_converter! {from_scan_code, to_scan_code,
+        Escape, 0x01,
+        Num1, 0x02,
+        Num2, 0x03,
+        Num3, 0x04,
+        Num4, 0x05,
+        Num5, 0x06,
+        Num6, 0x07,
+        Num7, 0x08,
+        Num8, 0x09,
+        Num9, 0x0A,
+        Num0, 0x0B,
+        Minus, 0x0C,
+        Equal, 0x0D,
+        Backspace, 0x0E,
+        Tab, 0x0F,
+        KeyQ, 0x10,
+        KeyW, 0x11,
+        KeyE, 0x12,
+        KeyR, 0x13,
+        KeyT, 0x14,
+        KeyY, 0x15,
+        KeyU, 0x16,
+        KeyI, 0x17,
+        KeyO, 0x18,
+        KeyP, 0x19,
+        LeftBracket, 0x1A,
+        RightBracket, 0x1B,
+        Enter, 0x1C,
+        ControlLeft, 0x1D,
+        KeyA, 0x1E,
+        KeyS, 0x1F,
+        KeyD, 0x20,
+        KeyF, 0x21,
+        KeyG, 0x22,
+        KeyH, 0x23,
+        KeyJ, 0x24,
+        KeyK, 0x25,
+        KeyL, 0x26,
+        Semicolon, 0x27,
+        Quote, 0x28,
+        Backquote, 0x29,
+        ShiftLeft, 0x2A,
+        Backslash, 0x2B,
+        KeyZ, 0x2C,
+        KeyX, 0x2D,
+        KeyC, 0x2E,
+        KeyV, 0x2F,
+        KeyB, 0x30,
+        KeyN, 0x31,
+        KeyM, 0x32,
+        Comma, 0x33,
+        Dot, 0x34,
+        Slash, 0x35,
+        ShiftRight, 0x36,
+        NumpadMultiply, 0x37,
+        AltLeft, 0x38,
+        Space, 0x39,
+        Capslock, 0x3A,
+        F1, 0x3B,
+        F2, 0x3C,
+        F3, 0x3D,
+        F4, 0x3E,
+        F5, 0x3F,
+        F6, 0x40,
+        F7, 0x41,
+        F8, 0x42,
+        F9, 0x43,
+        F10, 0x44,
+        Pause, 0x45,
+        ScrollLock, 0x46,
+        Numpad7, 0x47,
+        Numpad8, 0x48,
+        Numpad9, 0x49,
+        NumpadSubtract, 0x4A,
+        Numpad4, 0x4B,
+        Numpad5, 0x4C,
+        Numpad6, 0x4D,
+        NumpadAdd, 0x4E,
+        Numpad1, 0x4F,
+        Numpad2, 0x50,
+        Numpad3, 0x51,
+        Numpad0, 0x52,
+        NumpadDecimal, 0x53,
+        IntlBackslash, 0x56,
+        F11, 0x57,
+        F12, 0x58,
+        NumpadEqual, 0x59,
+        F13, 0x64,
+        F14, 0x65,
+        F15, 0x66,
+        F16, 0x67,
+        F17, 0x68,
+        F18, 0x69,
+        F19, 0x6A,
+        F20, 0x6B,
+        F21, 0x6C,
+        F22, 0x6D,
+        F23, 0x6E,
+        KanaMode, 0x70,
+        IntlRo, 0x73,
+        F24, 0x76,
+        Convert, 0x79,
+        NonConvert, 0x7B,
+        NumpadSeparator, 0x7E,
+        Hanja, 0xF1,
+        MediaPrevTrack, 0xE010,
+        MediaNextTrack, 0xE019,
+        NumpadEnter, 0xE01C,
+        ControlRight, 0xE01D,
+        VolumeMute, 0xE020,
+        LaunchApp2, 0xE021,
+        MediaPlayPause, 0xE022,
+        MediaStop, 0xE024,
+        VolumeDown, 0xE02E,
+        VolumeUp, 0xE030,
+        BrowserHome, 0xE032,
+        NumpadDivide, 0xE035,
+        Printscreen, 0xE037,
+        AltRight, 0xE038,
+        NumLock, 0xE045,
+        Home, 0xE047,
+        UpArrow, 0xE048,
+        PageUp, 0xE049,
+        LeftArrow, 0xE04B,
+        RightArrow, 0xE04D,
+        End, 0xE04F,
+        DownArrow, 0xE050,
+        PageDown, 0xE051,
+        Insert, 0xE052,
+        Delete, 0xE053,
+        MetaLeft, 0xE05B,
+        MetaRight, 0xE05C,
+        ContextMenu, 0xE05D,
+        Sleep, 0xE05F,
+        BrowserSearch, 0xE065,
+        BrowserFavorites, 0xE066,
+        BrowserRefresh, 0xE067,
+        BrowserStop, 0xE068,
+        BrowserForward, 0xE069,
+        BrowserBack, 0xE06A,
+        LaunchApp1, 0xE06B,
+        LaunchMail, 0xE06C,
+        LaunchMediaSelect, 0xE06D
     }
 
     pub fn to_str(&self) -> &str {
         self.as_ref()
     }
+
+    /// the left and right keys of an either-side key
+    pub fn get_sides(&self) -> Option<(KeyCode, KeyCode)> {
+        match self {
+            KeyCode::Control => Some((KeyCode::ControlLeft, KeyCode::ControlRight)),
+            KeyCode::Shift => Some((KeyCode::ShiftLeft, KeyCode::ShiftRight)),
+            KeyCode::Alt => Some((KeyCode::AltLeft, KeyCode::AltRight)),
+            KeyCode::Meta => Some((KeyCode::MetaLeft, KeyCode::MetaRight)),
+            _ => None,
+        }
+    }
+
+    pub fn is_either_side(&self) -> bool {
+        self.get_sides().is_some()
+    }
+
+    pub fn is_mouse_button(&self) -> bool {
+        matches!(
+            self,
+            KeyCode::MouseLeft
+                | KeyCode::MouseRight
+                | KeyCode::MouseMiddle
+                | KeyCode::MouseX1
+                | KeyCode::MouseX2
+        )
+    }
+
+    pub fn is_wheel(&self) -> bool {
+        matches!(
+            self,
+            KeyCode::WheelUp | KeyCode::WheelDown | KeyCode::WheelLeft | KeyCode::WheelRight
+        )
+    }
+
+    pub fn is_modifier(&self) -> bool {
+        match self {
+            KeyCode::ControlLeft
+            | KeyCode::ControlRight
+            | KeyCode::ShiftLeft
+            | KeyCode::ShiftRight
+            | KeyCode::AltLeft
+            | KeyCode::AltRight
+            | KeyCode::MetaLeft
+            | KeyCode::MetaRight => true,
+            _ => self.is_either_side(),
+        }
+    }
+
+    /// whether the physical key `key` satisfies `self`, which is the case when they are
+    /// the same key, or when `self` is an either-side key and `key` one of its sides
+    pub fn matches(&self, key: KeyCode) -> bool {
+        if *self == key {
+            return true;
+        }
+        match self.get_sides() {
+            Some((left, right)) => key == left || key == right,
+            None => false,
+        }
+    }
+
+    /// every key except `KeyCode::Unknown`
+    pub fn known_keys() -> impl Iterator<Item = KeyCode> {
+        <KeyCode as strum::IntoEnumIterator>::iter()
+            .filter(|key| !matches!(key, KeyCode::Unknown(_)))
+    }
 }
 
 impl Display for KeyCode {
@@ -226,7 +841,7 @@ impl Display for KeyCode {
     }
 }
 
-#[derive(strum_macros::AsRefStr, PartialEq, Clone, Copy)]
+#[derive(strum_macros::AsRefStr, PartialEq, Clone, Copy, Debug)]
 pub enum KeyOpt {
     Unknown,
     Up,
@@ -235,17 +850,57 @@ pub enum KeyOpt {
     DoubleClick,
 }
 
+/// `InputKey::wheel_delta` of one wheel notch, as on Windows
+pub const WHEEL_NOTCH: i32 = 120;
+
+#[derive(PartialEq, Clone, Copy, Debug)]
 pub struct InputKey {
+    /// the logical key, which depends on the keyboard layout
     pub key: KeyCode,
+    /// the key at the same position on a US keyboard, regardless of the layout,
+    /// see `KeyIdentity`; the same as `key` when the position isn't known
+    pub physical_key: KeyCode,
+    /// the backend's code for the position of the key: the set 1 scan code prefixed with
+    /// 0xE0 for extended keys on Windows, the evdev code on Linux, 0 when unknown
+    pub scan_code: u32,
     pub opt: KeyOpt,
+    /// milliseconds, on the same clock as `KBDLLHOOKSTRUCT.time`
+    pub time: u32,
+    /// cursor position of mouse events, when the backend knows it
+    pub pos: Option<CursorPos>,
+    /// distance of wheel keys in `WHEEL_NOTCH`s of 120, smaller steps come from
+    /// high-resolution wheels; the direction is given by the key
+    pub wheel_delta: i32,
+    /// the event was injected, e.g. through `SendInput`, rather than coming from a device
+    pub injected: bool,
+}
+
+impl InputKey {
+    pub fn new(key: KeyCode, opt: KeyOpt, time: u32) -> Self {
+        Self {
+            key,
+            physical_key: key,
+            opt,
+            time,
+            ..Self::default()
+        }
+    }
+
+    pub fn is_mouse_event(&self) -> bool {
+        self.key.is_mouse_button() || self.key.is_wheel() || self.opt == KeyOpt::Move
+    }
 }
 
+#[cfg(windows)]
 impl InputKey {
     pub fn from(wparam: WPARAM, l_param: LPARAM) -> Option<Self> {
         match wparam.0 as u32 {
-            WM_KEYUP | WM_KEYDOWN | WM_SYSKEYDOWN => Some(Self::from_keyboard(wparam, l_param)),
+            WM_KEYUP | WM_KEYDOWN | WM_SYSKEYUP | WM_SYSKEYDOWN => {
+                Some(Self::from_keyboard(wparam, l_param))
+            }
             WM_LBUTTONDBLCLK | WM_LBUTTONUP | WM_LBUTTONDOWN | WM_RBUTTONDBLCLK | WM_RBUTTONUP
-            | WM_RBUTTONDOWN => Some(Self::from_mouse(wparam, l_param)),
+            | WM_RBUTTONDOWN | WM_MBUTTONUP | WM_MBUTTONDOWN | WM_XBUTTONUP | WM_XBUTTONDOWN
+            | WM_MOUSEWHEEL | WM_MOUSEHWHEEL | WM_MOUSEMOVE => Self::from_mouse(wparam, l_param),
             _ => None,
         }
     }
@@ -253,7 +908,21 @@ impl InputKey {
     fn from_keyboard(wparam: WPARAM, l_param: LPARAM) -> Self {
         let kb_struct: &KBDLLHOOKSTRUCT = unsafe { &*(l_param.0 as *const KBDLLHOOKSTRUCT) };
         let mut key_event = Self::default();
+        let is_extended = kb_struct.flags.0 & LLKHF_EXTENDED.0 != 0;
         key_event.key = KeyCode::from_windows_id(kb_struct.vkCode);
+        if key_event.key == KeyCode::Enter && is_extended {
+            key_event.key = KeyCode::NumpadEnter;
+        }
+        // injected events often come without a scan code
+        if kb_struct.scanCode != 0 {
+            key_event.scan_code = kb_struct.scanCode | if is_extended { 0xE000 } else { 0 };
+        }
+        key_event.physical_key = match KeyCode::from_scan_code(key_event.scan_code) {
+            KeyCode::Unknown(_) => key_event.key,
+            physical_key => physical_key,
+        };
+        key_event.time = kb_struct.time;
+        key_event.injected = kb_struct.flags.0 & LLKHF_INJECTED.0 != 0;
         let id = wparam.0 as u32;
         if id == WM_KEYDOWN || id == WM_SYSKEYDOWN {
             key_event.opt = KeyOpt::Down;
@@ -263,46 +932,72 @@ impl InputKey {
         key_event
     }
 
-    fn from_mouse(wparam: WPARAM, l_param: LPARAM) -> Self {
-        // let mouse_struct: &MOUSEHOOKSTRUCT = unsafe { &*(l_param.0 as *const MOUSEHOOKSTRUCT) };
+    fn from_mouse(wparam: WPARAM, l_param: LPARAM) -> Option<Self> {
+        let mouse_struct: &MSLLHOOKSTRUCT = unsafe { &*(l_param.0 as *const MSLLHOOKSTRUCT) };
         let mut mouse_event = Self::default();
-        match wparam.0 as u32 {
-            WM_LBUTTONDBLCLK => {
-                mouse_event.opt = KeyOpt::DoubleClick;
-                mouse_event.key = KeyCode::MouseLeft;
-            }
-            WM_LBUTTONUP => {
-                mouse_event.opt = KeyOpt::Up;
-                mouse_event.key = KeyCode::MouseLeft;
-            }
-            WM_LBUTTONDOWN => {
-                mouse_event.opt = KeyOpt::Down;
-                mouse_event.key = KeyCode::MouseLeft;
+        mouse_event.time = mouse_struct.time;
+        mouse_event.pos = Some(CursorPos::from(mouse_struct.pt));
+        mouse_event.injected = mouse_struct.flags & LLMHF_INJECTED != 0;
+        // the high word of `mouseData` is the wheel delta or the X button
+        let high_word = (mouse_struct.mouseData >> 16) as u16;
+        let (key, opt) = match wparam.0 as u32 {
+            WM_LBUTTONDBLCLK => (KeyCode::MouseLeft, KeyOpt::DoubleClick),
+            WM_LBUTTONUP => (KeyCode::MouseLeft, KeyOpt::Up),
+            WM_LBUTTONDOWN => (KeyCode::MouseLeft, KeyOpt::Down),
+            WM_RBUTTONDBLCLK => (KeyCode::MouseRight, KeyOpt::DoubleClick),
+            WM_RBUTTONUP => (KeyCode::MouseRight, KeyOpt::Up),
+            WM_RBUTTONDOWN => (KeyCode::MouseRight, KeyOpt::Down),
+            WM_MBUTTONUP => (KeyCode::MouseMiddle, KeyOpt::Up),
+            WM_MBUTTONDOWN => (KeyCode::MouseMiddle, KeyOpt::Down),
+            WM_XBUTTONUP | WM_XBUTTONDOWN => {
+                let key = match high_word {
+                    XBUTTON1 => KeyCode::MouseX1,
+                    XBUTTON2 => KeyCode::MouseX2,
+                    _ => return None,
+                };
+                if wparam.0 as u32 == WM_XBUTTONUP {
+                    (key, KeyOpt::Up)
+                } else {
+                    (key, KeyOpt::Down)
+                }
             }
-            WM_RBUTTONDBLCLK => {
-                mouse_event.opt = KeyOpt::DoubleClick;
-                mouse_event.key = KeyCode::MouseRight;
+            WM_MOUSEWHEEL | WM_MOUSEHWHEEL => {
+                let delta = high_word as i16 as i32;
+                if delta == 0 {
+                    return None;
+                }
+                mouse_event.wheel_delta = delta.abs();
+                // positive is away from the user, and to the right
+                let key = match (wparam.0 as u32 == WM_MOUSEWHEEL, delta > 0) {
+                    (true, true) => KeyCode::WheelUp,
+                    (true, false) => KeyCode::WheelDown,
+                    (false, true) => KeyCode::WheelRight,
+                    (false, false) => KeyCode::WheelLeft,
+                };
+                (key, KeyOpt::Down)
             }
-            WM_RBUTTONUP => {
-                mouse_event.opt = KeyOpt::Up;
-                mouse_event.key = KeyCode::MouseRight;
-            }
-            WM_RBUTTONDOWN => {
-                mouse_event.opt = KeyOpt::Down;
-                mouse_event.key = KeyCode::MouseRight;
-            }
-            WM_MOUSEMOVE => {
-                mouse_event.opt = KeyOpt::Move;
-            }
-            _ => (),
-        }
-        mouse_event
+            WM_MOUSEMOVE => (KeyCode::Unknown(0), KeyOpt::Move),
+            _ => return None,
+        };
+        mouse_event.key = key;
+        mouse_event.physical_key = key;
+        mouse_event.opt = opt;
+        Some(mouse_event)
     }
 }
 
 impl Default for InputKey {
     fn default() -> Self {
-        unsafe { core::mem::zeroed() }
+        Self {
+            key: KeyCode::Unknown(0),
+            physical_key: KeyCode::Unknown(0),
+            scan_code: 0,
+            opt: KeyOpt::Unknown,
+            time: 0,
+            pos: None,
+            wheel_delta: 0,
+            injected: false,
+        }
     }
 }
 
@@ -310,19 +1005,21 @@ impl Display for InputKey {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "InputKey:{{opt:{}, key:{}}}",
+            "InputKey:{{opt:{}, key:{}, time:{}}}",
             self.opt.as_ref(),
             self.key,
+            self.time,
         )
     }
 }
 
-#[derive(PartialEq, Clone, Copy)]
+#[derive(PartialEq, Clone, Copy, Debug)]
 pub struct CursorPos {
     pub x: i32,
     pub y: i32,
 }
 
+#[cfg(windows)]
 impl CursorPos {
     pub fn get_cursor_pos() -> Self {
         let mut point = POINT::default();
@@ -333,6 +1030,7 @@ impl CursorPos {
     }
 }
 
+#[cfg(windows)]
 impl From<POINT> for CursorPos {
     fn from(value: POINT) -> Self {
         CursorPos {
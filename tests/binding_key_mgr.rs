@@ -0,0 +1,1387 @@
+use inbot::*;
+
+fn key(key: KeyCode, modifer_keys: &[KeyCode]) -> BindingKey {
+    BindingKey {
+        key,
+        modifer_keys: modifer_keys.to_vec(),
+    }
+}
+
+fn bind_with(listener: &mut ScriptedListener, input: &str, options: BindingOptions) -> u32 {
+    listener
+        .proxy()
+        .bind_multi_with(parse_sequence(input).unwrap(), options, Box::new(|| {}))
+        .unwrap()
+        .detach()
+}
+
+#[test]
+fn single_key_triggers_on_every_press() {
+    let mut listener = ScriptedListener::new();
+    let uid = listener
+        .proxy()
+        .bind_multi(vec![key(KeyCode::KeyA, &[])], Box::new(|| {}))
+        .unwrap()
+        .detach();
+    listener.play(
+        &Script::new()
+            .tap(KeyCode::KeyA)
+            .tap(KeyCode::KeyB)
+            .tap(KeyCode::KeyA),
+    );
+    listener.assert_triggered(uid, 2);
+}
+
+#[test]
+fn bind_once_triggers_only_once() {
+    let mut listener = ScriptedListener::new();
+    let uid = listener
+        .proxy()
+        .bind_once(vec![key(KeyCode::KeyA, &[])], Box::new(|| {}))
+        .unwrap()
+        .detach();
+    listener.play(&Script::new().tap(KeyCode::KeyA));
+    listener.play(&Script::new().tap(KeyCode::KeyA));
+    listener.assert_triggered(uid, 1);
+}
+
+#[test]
+fn chord_requires_its_modifers() {
+    let mut listener = ScriptedListener::new();
+    let uid = listener
+        .proxy()
+        .bind_multi(
+            vec![key(KeyCode::KeyF, &[KeyCode::ControlLeft])],
+            Box::new(|| {}),
+        )
+        .unwrap()
+        .detach();
+    listener.play(
+        &Script::new()
+            .tap(KeyCode::KeyF)
+            .chord(&[KeyCode::ShiftLeft], KeyCode::KeyF)
+            .chord(&[KeyCode::ControlLeft, KeyCode::ShiftLeft], KeyCode::KeyF),
+    );
+    listener.assert_not_triggered(uid);
+    listener.play(&Script::new().chord(&[KeyCode::ControlLeft], KeyCode::KeyF));
+    listener.assert_triggered(uid, 1);
+}
+
+#[test]
+fn sequence_triggers_after_last_key() {
+    let mut listener = ScriptedListener::new();
+    let modifer_keys = [KeyCode::ControlLeft, KeyCode::ShiftLeft];
+    let uid = listener
+        .proxy()
+        .bind_multi(
+            vec![
+                key(KeyCode::KeyK, &modifer_keys),
+                key(KeyCode::KeyC, &modifer_keys),
+            ],
+            Box::new(|| {}),
+        )
+        .unwrap()
+        .detach();
+    let script = Script::new()
+        .down(KeyCode::ControlLeft)
+        .down(KeyCode::ShiftLeft)
+        .tap(KeyCode::KeyK);
+    listener.play(&script);
+    listener.assert_not_triggered(uid);
+    let script = Script::new()
+        .tap(KeyCode::KeyC)
+        .up(KeyCode::ShiftLeft)
+        .up(KeyCode::ControlLeft);
+    listener.play(&script);
+    listener.assert_triggered(uid, 1);
+}
+
+#[test]
+fn callbacks_are_called_through_update() {
+    let mut listener = ScriptedListener::new();
+    let counter = std::rc::Rc::new(std::cell::Cell::new(0));
+    let counter_copy = counter.clone();
+    listener
+        .proxy()
+        .bind_multi(
+            vec![key(KeyCode::Space, &[])],
+            Box::new(move || counter_copy.set(counter_copy.get() + 1)),
+        )
+        .unwrap()
+        .detach();
+    listener.play(
+        &Script::new()
+            .tap(KeyCode::Space)
+            .wait(50)
+            .tap(KeyCode::Space),
+    );
+    assert_eq!(counter.get(), 2);
+}
+
+#[test]
+fn either_side_modifer_accepts_both_sides() {
+    let mut listener = ScriptedListener::new();
+    let either_uid = listener
+        .proxy()
+        .bind_multi(parse_sequence("Ctrl+F").unwrap(), Box::new(|| {}))
+        .unwrap()
+        .detach();
+    let left_uid = listener
+        .proxy()
+        .bind_multi(parse_sequence("LCtrl+F").unwrap(), Box::new(|| {}))
+        .unwrap()
+        .detach();
+    listener.play(
+        &Script::new()
+            .chord(&[KeyCode::ControlLeft], KeyCode::KeyF)
+            .chord(&[KeyCode::ControlRight], KeyCode::KeyF),
+    );
+    listener.assert_triggered(either_uid, 2);
+    listener.assert_triggered(left_uid, 1);
+}
+
+#[test]
+fn either_side_key_accepts_both_sides() {
+    let mut listener = ScriptedListener::new();
+    let uid = listener
+        .proxy()
+        .bind_multi(parse_sequence("Ctrl+Shift").unwrap(), Box::new(|| {}))
+        .unwrap()
+        .detach();
+    listener.play(
+        &Script::new()
+            .chord(&[KeyCode::ControlRight], KeyCode::ShiftLeft)
+            .chord(&[KeyCode::ControlLeft], KeyCode::ShiftRight)
+            .chord(&[KeyCode::AltLeft], KeyCode::ShiftRight),
+    );
+    listener.assert_triggered(uid, 2);
+}
+
+#[test]
+fn exact_policy_rejects_any_extra_holding_key() {
+    let mut listener = ScriptedListener::new();
+    let uid = bind_with(
+        &mut listener,
+        "Ctrl+F",
+        BindingOptions {
+            match_policy: MatchPolicy::Exact,
+            ..Default::default()
+        },
+    );
+    listener.play(
+        &Script::new()
+            .chord(&[KeyCode::ControlLeft, KeyCode::ShiftLeft], KeyCode::KeyF)
+            .chord(&[KeyCode::MouseLeft, KeyCode::ControlLeft], KeyCode::KeyF),
+    );
+    listener.assert_not_triggered(uid);
+}
+
+#[test]
+fn subset_policy_allows_extra_holding_keys() {
+    let mut listener = ScriptedListener::new();
+    let uid = bind_with(
+        &mut listener,
+        "Ctrl+F",
+        BindingOptions {
+            match_policy: MatchPolicy::Subset,
+            ..Default::default()
+        },
+    );
+    listener.play(
+        &Script::new()
+            .chord(&[KeyCode::ControlLeft, KeyCode::ShiftLeft], KeyCode::KeyF)
+            .chord(&[KeyCode::MouseLeft, KeyCode::ControlLeft], KeyCode::KeyF)
+            .chord(&[KeyCode::ShiftLeft], KeyCode::KeyF),
+    );
+    listener.assert_triggered(uid, 2);
+}
+
+#[test]
+fn ignore_non_modifiers_policy_only_checks_modifers() {
+    let mut listener = ScriptedListener::new();
+    let uid = bind_with(
+        &mut listener,
+        "Ctrl+F",
+        BindingOptions {
+            match_policy: MatchPolicy::IgnoreNonModifiers,
+            ..Default::default()
+        },
+    );
+    listener.play(
+        &Script::new()
+            .chord(&[KeyCode::MouseLeft, KeyCode::ControlLeft], KeyCode::KeyF)
+            .chord(&[KeyCode::KeyG, KeyCode::ControlRight], KeyCode::KeyF)
+            .chord(&[KeyCode::ControlLeft, KeyCode::ShiftLeft], KeyCode::KeyF),
+    );
+    listener.assert_triggered(uid, 2);
+}
+
+#[test]
+fn sequence_expires_after_default_timeout() {
+    let mut listener = ScriptedListener::new();
+    let uid = bind_with(&mut listener, "K, C", BindingOptions::default());
+    let timeout = DEFAULT_SEQUENCE_TIMEOUT.as_millis() as u32;
+    listener.play(
+        &Script::new()
+            .tap(KeyCode::KeyK)
+            .wait(timeout + 1)
+            .tap(KeyCode::KeyC),
+    );
+    listener.assert_not_triggered(uid);
+    listener.play(
+        &Script::new()
+            .tap(KeyCode::KeyK)
+            .wait(timeout)
+            .tap(KeyCode::KeyC),
+    );
+    listener.assert_triggered(uid, 1);
+}
+
+#[test]
+fn expired_sequence_can_restart_with_the_same_key() {
+    let mut listener = ScriptedListener::new();
+    let uid = bind_with(
+        &mut listener,
+        "K, C",
+        BindingOptions {
+            sequence_timeout: Some(std::time::Duration::from_millis(100)),
+            ..Default::default()
+        },
+    );
+    listener.play(
+        &Script::new()
+            .tap(KeyCode::KeyK)
+            .wait(500)
+            .tap(KeyCode::KeyK)
+            .wait(50)
+            .tap(KeyCode::KeyC),
+    );
+    listener.assert_triggered(uid, 1);
+}
+
+#[test]
+fn binding_timeout_overrides_default_timeout() {
+    let mut listener = ScriptedListener::new();
+    let short_uid = bind_with(
+        &mut listener,
+        "K, C",
+        BindingOptions {
+            sequence_timeout: Some(std::time::Duration::from_millis(100)),
+            ..Default::default()
+        },
+    );
+    let long_uid = bind_with(
+        &mut listener,
+        "K, C",
+        BindingOptions {
+            sequence_timeout: Some(std::time::Duration::from_millis(10_000)),
+            ..Default::default()
+        },
+    );
+    listener.play(
+        &Script::new()
+            .tap(KeyCode::KeyK)
+            .wait(5_000)
+            .tap(KeyCode::KeyC),
+    );
+    listener.assert_not_triggered(short_uid);
+    listener.assert_triggered(long_uid, 1);
+}
+
+#[test]
+fn default_timeout_can_be_disabled() {
+    let mut listener = ScriptedListener::new();
+    listener.proxy().set_default_sequence_timeout(None).unwrap();
+    let uid = bind_with(&mut listener, "K, C", BindingOptions::default());
+    listener.play(
+        &Script::new()
+            .tap(KeyCode::KeyK)
+            .wait(600_000)
+            .tap(KeyCode::KeyC),
+    );
+    listener.assert_triggered(uid, 1);
+}
+
+#[test]
+fn overlapping_sequences_both_trigger() {
+    let mut listener = ScriptedListener::new();
+    let ab_uid = bind_with(&mut listener, "A, B", BindingOptions::default());
+    let bc_uid = bind_with(&mut listener, "B, C", BindingOptions::default());
+    listener.play(
+        &Script::new()
+            .tap(KeyCode::KeyA)
+            .tap(KeyCode::KeyB)
+            .tap(KeyCode::KeyC),
+    );
+    listener.assert_triggered(ab_uid, 1);
+    listener.assert_triggered(bc_uid, 1);
+}
+
+#[test]
+fn failed_prefix_restarts_from_the_failing_key() {
+    let mut listener = ScriptedListener::new();
+    let uid = bind_with(&mut listener, "A, B", BindingOptions::default());
+    listener.play(
+        &Script::new()
+            .tap(KeyCode::KeyA)
+            .tap(KeyCode::KeyA)
+            .tap(KeyCode::KeyB),
+    );
+    listener.assert_triggered(uid, 1);
+    listener.clear_triggered_counts();
+    listener.play(
+        &Script::new()
+            .tap(KeyCode::KeyA)
+            .tap(KeyCode::KeyC)
+            .tap(KeyCode::KeyB)
+            .tap(KeyCode::KeyA)
+            .tap(KeyCode::KeyB),
+    );
+    listener.assert_triggered(uid, 1);
+}
+
+#[test]
+fn repeated_prefix_keeps_every_partial_match() {
+    let mut listener = ScriptedListener::new();
+    let uid = bind_with(&mut listener, "A, A, B", BindingOptions::default());
+    listener.play(
+        &Script::new()
+            .tap(KeyCode::KeyA)
+            .tap(KeyCode::KeyA)
+            .tap(KeyCode::KeyA)
+            .tap(KeyCode::KeyB),
+    );
+    listener.assert_triggered(uid, 1);
+}
+
+#[test]
+fn longest_completed_sequence_wins() {
+    let mut listener = ScriptedListener::new();
+    let ab_uid = bind_with(&mut listener, "A, B", BindingOptions::default());
+    let b_uid = bind_with(&mut listener, "B", BindingOptions::default());
+    listener.play(&Script::new().tap(KeyCode::KeyA).tap(KeyCode::KeyB));
+    listener.assert_triggered(ab_uid, 1);
+    listener.assert_not_triggered(b_uid);
+    listener.play(&Script::new().tap(KeyCode::KeyB));
+    listener.assert_triggered(b_uid, 1);
+}
+
+#[test]
+fn repressing_modifers_keeps_the_sequence() {
+    let mut listener = ScriptedListener::new();
+    let uid = bind_with(&mut listener, "Ctrl+K, Ctrl+C", BindingOptions::default());
+    listener.play(
+        &Script::new()
+            .chord(&[KeyCode::ControlLeft], KeyCode::KeyK)
+            .chord(&[KeyCode::ControlRight], KeyCode::KeyC),
+    );
+    listener.assert_triggered(uid, 1);
+}
+
+#[test]
+fn ambiguous_prefix_waits_for_the_next_key() {
+    let mut listener = ScriptedListener::new();
+    let short_uid = bind_with(&mut listener, "Ctrl+K", BindingOptions::default());
+    let long_uid = bind_with(&mut listener, "Ctrl+K, Ctrl+C", BindingOptions::default());
+    let script = Script::new().down(KeyCode::ControlLeft).tap(KeyCode::KeyK);
+    listener.play(&script);
+    listener.assert_not_triggered(short_uid);
+    assert!(listener.proxy().is_waiting_for_next_key());
+    assert_eq!(listener.proxy().get_pending_uids(), vec![short_uid]);
+    listener.play(&Script::new().tap(KeyCode::KeyC).up(KeyCode::ControlLeft));
+    listener.assert_not_triggered(short_uid);
+    listener.assert_triggered(long_uid, 1);
+    assert!(!listener.proxy().is_waiting_for_next_key());
+}
+
+#[test]
+fn ambiguous_prefix_triggers_on_other_key() {
+    let mut listener = ScriptedListener::new();
+    let short_uid = bind_with(&mut listener, "Ctrl+K", BindingOptions::default());
+    let long_uid = bind_with(&mut listener, "Ctrl+K, Ctrl+C", BindingOptions::default());
+    listener.play(
+        &Script::new()
+            .chord(&[KeyCode::ControlLeft], KeyCode::KeyK)
+            .tap(KeyCode::KeyX),
+    );
+    listener.assert_triggered(short_uid, 1);
+    listener.assert_not_triggered(long_uid);
+    assert!(!listener.proxy().is_waiting_for_next_key());
+}
+
+#[test]
+fn ambiguous_prefix_triggers_after_timeout() {
+    let mut listener = ScriptedListener::new();
+    listener
+        .proxy()
+        .set_ambiguous_timeout(std::time::Duration::from_millis(200))
+        .unwrap();
+    let short_uid = bind_with(&mut listener, "Ctrl+K", BindingOptions::default());
+    let long_uid = bind_with(&mut listener, "Ctrl+K, Ctrl+C", BindingOptions::default());
+    let script = Script::new()
+        .chord(&[KeyCode::ControlLeft], KeyCode::KeyK)
+        .wait(199);
+    listener.play(&script);
+    listener.assert_not_triggered(short_uid);
+    // every script starts at time 0
+    listener.play(&Script::new().wait(200));
+    listener.assert_triggered(short_uid, 1);
+    assert!(!listener.proxy().is_waiting_for_next_key());
+    // the longer binding is given up once the shorter one triggered
+    let script = Script::new()
+        .wait(300)
+        .chord(&[KeyCode::ControlLeft], KeyCode::KeyC);
+    listener.play(&script);
+    listener.assert_triggered(short_uid, 1);
+    listener.assert_not_triggered(long_uid);
+}
+
+#[test]
+fn ambiguous_prefix_triggers_once_the_longer_sequence_expires() {
+    let mut listener = ScriptedListener::new();
+    listener
+        .proxy()
+        .set_default_sequence_timeout(Some(std::time::Duration::from_millis(200)))
+        .unwrap();
+    let short_uid = bind_with(&mut listener, "Ctrl+K", BindingOptions::default());
+    let long_uid = bind_with(&mut listener, "Ctrl+K, Ctrl+C", BindingOptions::default());
+    let script = Script::new()
+        .down(KeyCode::ControlLeft)
+        .tap(KeyCode::KeyK)
+        .wait(400)
+        .tap(KeyCode::KeyC)
+        .up(KeyCode::ControlLeft);
+    listener.play(&script);
+    listener.assert_triggered(short_uid, 1);
+    listener.assert_not_triggered(long_uid);
+    assert!(!listener.proxy().is_waiting_for_next_key());
+
+    let mut listener = ScriptedListener::new();
+    let short_uid = bind_with(&mut listener, "K", BindingOptions::default());
+    let long_uid = bind_with(
+        &mut listener,
+        "K, C",
+        BindingOptions {
+            sequence_timeout: Some(std::time::Duration::from_millis(100)),
+            ..Default::default()
+        },
+    );
+    listener.play(
+        &Script::new()
+            .tap(KeyCode::KeyK)
+            .wait(400)
+            .tap(KeyCode::KeyC),
+    );
+    listener.assert_triggered(short_uid, 1);
+    listener.assert_not_triggered(long_uid);
+}
+
+#[test]
+fn release_trigger_fires_when_the_key_is_released() {
+    let mut listener = ScriptedListener::new();
+    let uid = bind_with(
+        &mut listener,
+        "F1",
+        BindingOptions {
+            trigger: Trigger::Release,
+            ..Default::default()
+        },
+    );
+    // auto-repeat while held doesn't trigger again
+    let script = Script::new()
+        .down(KeyCode::F1)
+        .wait(30)
+        .down(KeyCode::F1)
+        .down(KeyCode::F1);
+    listener.play(&script);
+    listener.assert_not_triggered(uid);
+    listener.play(&Script::new().up(KeyCode::F1));
+    listener.assert_triggered(uid, 1);
+}
+
+#[test]
+fn hold_trigger_fires_after_the_duration() {
+    let mut listener = ScriptedListener::new();
+    let trigger = Trigger::Hold {
+        duration: std::time::Duration::from_millis(500),
+        repeat: None,
+    };
+    let uid = bind_with(
+        &mut listener,
+        "Ctrl+Space",
+        BindingOptions {
+            trigger,
+            ..Default::default()
+        },
+    );
+    listener.play(&Script::new().chord(&[KeyCode::ControlLeft], KeyCode::Space));
+    listener.assert_not_triggered(uid);
+
+    let script = Script::new()
+        .down(KeyCode::ControlLeft)
+        .down(KeyCode::Space)
+        .wait(499);
+    listener.play(&script);
+    listener.assert_not_triggered(uid);
+    listener.play(&Script::new().wait(2000).up(KeyCode::Space));
+    listener.assert_triggered(uid, 1);
+}
+
+#[test]
+fn hold_trigger_repeats_until_released() {
+    let mut listener = ScriptedListener::new();
+    let trigger = Trigger::Hold {
+        duration: std::time::Duration::from_millis(500),
+        repeat: Some(std::time::Duration::from_millis(100)),
+    };
+    let uid = bind_with(
+        &mut listener,
+        "F1",
+        BindingOptions {
+            trigger,
+            ..Default::default()
+        },
+    );
+    listener.play(&Script::new().down(KeyCode::F1).wait(500));
+    listener.assert_triggered(uid, 1);
+    listener.play(&Script::new().wait(750));
+    listener.assert_triggered(uid, 3);
+    listener.play(&Script::new().wait(800).up(KeyCode::F1).wait(500));
+    listener.assert_triggered(uid, 4);
+}
+
+#[test]
+fn release_trigger_prefix_waits_for_the_next_key() {
+    let mut listener = ScriptedListener::new();
+    let release_uid = bind_with(
+        &mut listener,
+        "K",
+        BindingOptions {
+            trigger: Trigger::Release,
+            ..Default::default()
+        },
+    );
+    let long_uid = bind_with(&mut listener, "K, C", BindingOptions::default());
+    listener.play(&Script::new().tap(KeyCode::KeyK));
+    listener.assert_not_triggered(release_uid);
+    assert_eq!(listener.proxy().get_pending_uids(), vec![release_uid]);
+    listener.play(&Script::new().tap(KeyCode::KeyC));
+    listener.assert_not_triggered(release_uid);
+    listener.assert_triggered(long_uid, 1);
+    assert!(!listener.proxy().is_waiting_for_next_key());
+
+    // another key triggers it at once, since the key was already released
+    listener.play(&Script::new().tap(KeyCode::KeyK).tap(KeyCode::KeyX));
+    listener.assert_triggered(release_uid, 1);
+    // a key still held when the wait ends triggers on release as usual
+    let script = Script::new()
+        .down(KeyCode::KeyK)
+        .wait(DEFAULT_AMBIGUOUS_TIMEOUT.as_millis() as u32);
+    listener.play(&script);
+    listener.assert_triggered(release_uid, 1);
+    assert!(!listener.proxy().is_waiting_for_next_key());
+    listener.play(&Script::new().up(KeyCode::KeyK));
+    listener.assert_triggered(release_uid, 2);
+    listener.assert_triggered(long_uid, 1);
+}
+
+#[test]
+fn dual_role_key_triggers_on_tap() {
+    let mut listener = ScriptedListener::new();
+    let uid = listener
+        .proxy()
+        .bind_dual_role(KeyCode::Capslock, KeyCode::Control, Box::new(|| {}))
+        .unwrap()
+        .detach();
+    let script = Script::new()
+        .down(KeyCode::Capslock)
+        .wait(50)
+        .down(KeyCode::Capslock)
+        .wait(50)
+        .up(KeyCode::Capslock);
+    listener.play(&script);
+    listener.assert_triggered(uid, 1);
+}
+
+#[test]
+fn dual_role_key_acts_as_modifer_when_held() {
+    let mut listener = ScriptedListener::new();
+    let tap_uid = listener
+        .proxy()
+        .bind_dual_role(KeyCode::Capslock, KeyCode::Control, Box::new(|| {}))
+        .unwrap()
+        .detach();
+    let copy_uid = bind_with(&mut listener, "Ctrl+C", BindingOptions::default());
+    listener.play(&Script::new().chord(&[KeyCode::Capslock], KeyCode::KeyC));
+    listener.assert_triggered(copy_uid, 1);
+    listener.assert_not_triggered(tap_uid);
+}
+
+#[test]
+fn dual_role_key_held_past_tapping_term_is_not_a_tap() {
+    let mut listener = ScriptedListener::new();
+    let uid = listener
+        .proxy()
+        .bind_dual_role(KeyCode::Capslock, KeyCode::Control, Box::new(|| {}))
+        .unwrap()
+        .detach();
+    let script = Script::new()
+        .down(KeyCode::Capslock)
+        .wait(DEFAULT_TAPPING_TERM.as_millis() as u32)
+        .up(KeyCode::Capslock);
+    listener.play(&script);
+    listener.assert_not_triggered(uid);
+}
+
+#[test]
+fn double_tap_does_not_trigger_single_tap() {
+    let mut listener = ScriptedListener::new();
+    let single_uid = bind_with(
+        &mut listener,
+        "Shift",
+        BindingOptions {
+            trigger: Trigger::multi_tap(1),
+            ..Default::default()
+        },
+    );
+    let double_uid = bind_with(
+        &mut listener,
+        "Shift",
+        BindingOptions {
+            trigger: Trigger::multi_tap(2),
+            ..Default::default()
+        },
+    );
+    let script = Script::new()
+        .tap(KeyCode::ShiftLeft)
+        .wait(100)
+        .tap(KeyCode::ShiftRight);
+    listener.play(&script);
+    listener.assert_not_triggered(single_uid);
+    listener.assert_triggered(double_uid, 1);
+}
+
+#[test]
+fn single_tap_triggers_after_the_interval() {
+    let mut listener = ScriptedListener::new();
+    let single_uid = bind_with(
+        &mut listener,
+        "Shift",
+        BindingOptions {
+            trigger: Trigger::multi_tap(1),
+            ..Default::default()
+        },
+    );
+    let double_uid = bind_with(
+        &mut listener,
+        "Shift",
+        BindingOptions {
+            trigger: Trigger::multi_tap(2),
+            ..Default::default()
+        },
+    );
+    let interval = DEFAULT_TAP_INTERVAL.as_millis() as u32;
+    listener.play(&Script::new().tap(KeyCode::ShiftLeft).wait(interval));
+    listener.assert_not_triggered(single_uid);
+    listener.play(&Script::new().wait(interval + 1));
+    listener.assert_triggered(single_uid, 1);
+    listener.assert_not_triggered(double_uid);
+}
+
+#[test]
+fn other_key_resolves_a_waiting_single_tap() {
+    let mut listener = ScriptedListener::new();
+    let single_uid = bind_with(
+        &mut listener,
+        "Shift",
+        BindingOptions {
+            trigger: Trigger::multi_tap(1),
+            ..Default::default()
+        },
+    );
+    let double_uid = bind_with(
+        &mut listener,
+        "Shift",
+        BindingOptions {
+            trigger: Trigger::multi_tap(2),
+            ..Default::default()
+        },
+    );
+    let script = Script::new()
+        .tap(KeyCode::ShiftLeft)
+        .tap(KeyCode::KeyA)
+        .tap(KeyCode::ShiftLeft);
+    listener.play(&script);
+    listener.assert_triggered(single_uid, 1);
+    listener.assert_not_triggered(double_uid);
+}
+
+#[test]
+fn chord_is_not_a_single_tap() {
+    let mut listener = ScriptedListener::new();
+    let uid = bind_with(
+        &mut listener,
+        "Shift",
+        BindingOptions {
+            trigger: Trigger::multi_tap(1),
+            ..Default::default()
+        },
+    );
+    listener.play(&Script::new().chord(&[KeyCode::ShiftLeft], KeyCode::KeyA));
+    listener.play(&Script::new().wait(DEFAULT_TAP_INTERVAL.as_millis() as u32 + 1));
+    listener.assert_not_triggered(uid);
+}
+
+#[test]
+fn chord_is_not_the_first_of_a_double_tap() {
+    let mut listener = ScriptedListener::new();
+    let uid = bind_with(
+        &mut listener,
+        "Shift",
+        BindingOptions {
+            trigger: Trigger::multi_tap(2),
+            ..Default::default()
+        },
+    );
+    let script = Script::new()
+        .chord(&[KeyCode::ShiftLeft], KeyCode::KeyA)
+        .wait(100)
+        .tap(KeyCode::ShiftLeft);
+    listener.play(&script);
+    listener.assert_not_triggered(uid);
+}
+
+#[test]
+fn triple_tap_needs_quick_taps() {
+    let mut listener = ScriptedListener::new();
+    let uid = bind_with(
+        &mut listener,
+        "MouseLeft",
+        BindingOptions {
+            trigger: Trigger::multi_tap(3),
+            ..Default::default()
+        },
+    );
+    let interval = DEFAULT_TAP_INTERVAL.as_millis() as u32;
+    let script = Script::new()
+        .tap(KeyCode::MouseLeft)
+        .tap(KeyCode::MouseLeft)
+        .wait(interval + 1)
+        .tap(KeyCode::MouseLeft);
+    listener.play(&script);
+    listener.assert_not_triggered(uid);
+    let script = Script::new()
+        .wait(interval * 3)
+        .tap(KeyCode::MouseLeft)
+        .wait(100)
+        .tap(KeyCode::MouseLeft)
+        .wait(100)
+        .tap(KeyCode::MouseLeft);
+    listener.play(&script);
+    listener.assert_triggered(uid, 1);
+}
+
+fn passed_keys(listener: &mut ScriptedListener) -> Vec<(KeyCode, KeyOpt)> {
+    listener
+        .take_passed_keys()
+        .into_iter()
+        .map(|input_key| (input_key.key, input_key.opt))
+        .collect()
+}
+
+#[test]
+fn consuming_binding_swallows_its_key() {
+    let mut listener = ScriptedListener::new();
+    let uid = bind_with(
+        &mut listener,
+        "Ctrl+F",
+        BindingOptions {
+            consume: true,
+            ..Default::default()
+        },
+    );
+    listener.play(&Script::new().chord(&[KeyCode::ControlLeft], KeyCode::KeyF));
+    listener.assert_triggered(uid, 1);
+    assert_eq!(
+        passed_keys(&mut listener),
+        vec![
+            (KeyCode::ControlLeft, KeyOpt::Down),
+            (KeyCode::ControlLeft, KeyOpt::Up),
+        ]
+    );
+    listener.play(&Script::new().tap(KeyCode::KeyF));
+    assert_eq!(
+        passed_keys(&mut listener),
+        vec![(KeyCode::KeyF, KeyOpt::Down), (KeyCode::KeyF, KeyOpt::Up)]
+    );
+}
+
+#[test]
+fn failed_consuming_sequence_replays_its_keys() {
+    let mut listener = ScriptedListener::new();
+    let uid = bind_with(
+        &mut listener,
+        "G, G",
+        BindingOptions {
+            consume: true,
+            ..Default::default()
+        },
+    );
+    listener.play(&Script::new().tap(KeyCode::KeyG));
+    assert!(passed_keys(&mut listener).is_empty());
+    listener.play(&Script::new().tap(KeyCode::KeyX));
+    listener.assert_not_triggered(uid);
+    assert_eq!(
+        passed_keys(&mut listener),
+        vec![
+            (KeyCode::KeyG, KeyOpt::Down),
+            (KeyCode::KeyG, KeyOpt::Up),
+            (KeyCode::KeyX, KeyOpt::Down),
+            (KeyCode::KeyX, KeyOpt::Up),
+        ]
+    );
+    listener.play(&Script::new().tap(KeyCode::KeyG).tap(KeyCode::KeyG));
+    listener.assert_triggered(uid, 1);
+    assert!(passed_keys(&mut listener).is_empty());
+}
+
+#[test]
+fn expired_consuming_sequence_replays_its_keys() {
+    let mut listener = ScriptedListener::new();
+    bind_with(
+        &mut listener,
+        "G, G",
+        BindingOptions {
+            consume: true,
+            ..Default::default()
+        },
+    );
+    let timeout = DEFAULT_SEQUENCE_TIMEOUT.as_millis() as u32;
+    // the key is still held when the sequence expires, its release is let through
+    listener.play(&Script::new().down(KeyCode::KeyG).wait(timeout + 1));
+    assert_eq!(
+        passed_keys(&mut listener),
+        vec![(KeyCode::KeyG, KeyOpt::Down)]
+    );
+    listener.play(&Script::new().up(KeyCode::KeyG));
+    assert_eq!(
+        passed_keys(&mut listener),
+        vec![(KeyCode::KeyG, KeyOpt::Up)]
+    );
+}
+
+#[test]
+fn non_consuming_binding_lets_keys_through() {
+    let mut listener = ScriptedListener::new();
+    let uid = bind_with(&mut listener, "Ctrl+F", BindingOptions::default());
+    listener.play(&Script::new().chord(&[KeyCode::ControlLeft], KeyCode::KeyF));
+    listener.assert_triggered(uid, 1);
+    assert_eq!(passed_keys(&mut listener).len(), 4);
+}
+
+#[test]
+fn side_button_and_wheel_make_a_chord() {
+    let mut listener = ScriptedListener::new();
+    let uid = listener
+        .proxy()
+        .bind_multi(
+            vec![key(KeyCode::WheelUp, &[KeyCode::MouseX1])],
+            Box::new(|| {}),
+        )
+        .unwrap()
+        .detach();
+    listener.play(
+        &Script::new()
+            .wheel(KeyCode::WheelUp)
+            .down(KeyCode::MouseX1)
+            .wheel(KeyCode::WheelDown)
+            .up(KeyCode::MouseX1),
+    );
+    listener.assert_not_triggered(uid);
+    listener.play(
+        &Script::new()
+            .down(KeyCode::MouseX1)
+            .wheel(KeyCode::WheelUp)
+            .wheel(KeyCode::WheelUp)
+            .up(KeyCode::MouseX1),
+    );
+    listener.assert_triggered(uid, 2);
+}
+
+#[test]
+fn mouse_movement_does_not_break_sequences() {
+    let mut listener = ScriptedListener::new();
+    let uid = listener
+        .proxy()
+        .bind_multi(
+            vec![
+                key(KeyCode::KeyK, &[KeyCode::ControlLeft]),
+                key(KeyCode::KeyC, &[KeyCode::ControlLeft]),
+            ],
+            Box::new(|| {}),
+        )
+        .unwrap()
+        .detach();
+    listener.play(
+        &Script::new()
+            .chord(&[KeyCode::ControlLeft], KeyCode::KeyK)
+            .move_to(CursorPos { x: 10, y: 20 })
+            .chord(&[KeyCode::ControlLeft], KeyCode::KeyC),
+    );
+    listener.assert_triggered(uid, 1);
+}
+
+// what an AZERTY layout reports for the key at the position of Q on a US keyboard
+fn azerty_tap(script: Script, key: KeyCode, physical_key: KeyCode) -> Script {
+    let mut input_key = InputKey::new(key, KeyOpt::Down, script.get_now());
+    input_key.physical_key = physical_key;
+    let script = script.push(input_key);
+    script.push(InputKey {
+        opt: KeyOpt::Up,
+        ..input_key
+    })
+}
+
+#[test]
+fn physical_bindings_match_the_key_position() {
+    let mut listener = ScriptedListener::new();
+    let logical_uid = bind_with(
+        &mut listener,
+        "Ctrl+A",
+        BindingOptions {
+            key_identity: KeyIdentity::Logical,
+            ..Default::default()
+        },
+    );
+    let physical_uid = bind_with(
+        &mut listener,
+        "Ctrl+Q",
+        BindingOptions {
+            key_identity: KeyIdentity::Physical,
+            ..Default::default()
+        },
+    );
+    let other_uid = bind_with(
+        &mut listener,
+        "Ctrl+A",
+        BindingOptions {
+            key_identity: KeyIdentity::Physical,
+            ..Default::default()
+        },
+    );
+    let script = azerty_tap(
+        Script::new().down(KeyCode::ControlLeft),
+        KeyCode::KeyA,
+        KeyCode::KeyQ,
+    )
+    .up(KeyCode::ControlLeft);
+    listener.play(&script);
+    listener.assert_triggered(logical_uid, 1);
+    listener.assert_triggered(physical_uid, 1);
+    listener.assert_not_triggered(other_uid);
+}
+
+#[test]
+fn physical_sequences_ignore_the_layout() {
+    let mut listener = ScriptedListener::new();
+    let uid = bind_with(
+        &mut listener,
+        "Q, W",
+        BindingOptions {
+            key_identity: KeyIdentity::Physical,
+            ..Default::default()
+        },
+    );
+    let script = azerty_tap(Script::new(), KeyCode::KeyA, KeyCode::KeyQ);
+    let script = azerty_tap(script, KeyCode::KeyZ, KeyCode::KeyW);
+    listener.play(&script);
+    listener.assert_triggered(uid, 1);
+    listener.play(&Script::new().tap(KeyCode::KeyA).tap(KeyCode::KeyZ));
+    listener.assert_triggered(uid, 1);
+}
+
+#[test]
+fn disabled_binding_triggers_again_once_enabled() {
+    let mut listener = ScriptedListener::new();
+    let uid = bind_with(&mut listener, "A", BindingOptions::default());
+    listener.proxy().disable(uid).unwrap();
+    listener.play(&Script::new().tap(KeyCode::KeyA));
+    listener.assert_not_triggered(uid);
+    listener.proxy().enable(uid).unwrap();
+    listener.play(&Script::new().tap(KeyCode::KeyA));
+    listener.assert_triggered(uid, 1);
+    assert!(matches!(
+        listener.proxy().disable(uid + 1000),
+        Err(Error::UnknownBinding(_))
+    ));
+}
+
+#[test]
+fn disabling_drops_the_partial_sequence() {
+    let mut listener = ScriptedListener::new();
+    let uid = bind_with(&mut listener, "A, B", BindingOptions::default());
+    listener.play(&Script::new().tap(KeyCode::KeyA));
+    listener.proxy().disable(uid).unwrap();
+    listener.proxy().enable(uid).unwrap();
+    listener.play(&Script::new().tap(KeyCode::KeyB));
+    listener.assert_not_triggered(uid);
+    listener.play(&Script::new().tap(KeyCode::KeyA).tap(KeyCode::KeyB));
+    listener.assert_triggered(uid, 1);
+}
+
+#[test]
+fn pausing_replays_the_consumed_keys() {
+    let mut listener = ScriptedListener::new();
+    bind_with(
+        &mut listener,
+        "A, B",
+        BindingOptions {
+            consume: true,
+            ..Default::default()
+        },
+    );
+    listener.play(&Script::new().tap(KeyCode::KeyA));
+    assert_eq!(passed_keys(&mut listener), vec![]);
+    listener.proxy().pause().unwrap();
+    listener.play(&Script::new());
+    assert_eq!(
+        passed_keys(&mut listener),
+        vec![(KeyCode::KeyA, KeyOpt::Down), (KeyCode::KeyA, KeyOpt::Up)]
+    );
+    listener.play(&Script::new().tap(KeyCode::KeyA));
+    assert_eq!(
+        passed_keys(&mut listener),
+        vec![(KeyCode::KeyA, KeyOpt::Down), (KeyCode::KeyA, KeyOpt::Up)]
+    );
+}
+
+#[test]
+fn disabling_a_group_leaves_the_others() {
+    let mut listener = ScriptedListener::new();
+    let jump_uid = bind_with(
+        &mut listener,
+        "Space",
+        BindingOptions {
+            group: Some("game".to_string()),
+            ..Default::default()
+        },
+    );
+    let fire_uid = bind_with(
+        &mut listener,
+        "F",
+        BindingOptions {
+            group: Some("game".to_string()),
+            ..Default::default()
+        },
+    );
+    let menu_uid = bind_with(
+        &mut listener,
+        "Escape",
+        BindingOptions {
+            group: Some("menu".to_string()),
+            ..Default::default()
+        },
+    );
+    let script = Script::new()
+        .tap(KeyCode::Space)
+        .tap(KeyCode::KeyF)
+        .tap(KeyCode::Escape);
+    listener.proxy().disable_group("game").unwrap();
+    listener.play(&script);
+    listener.assert_not_triggered(jump_uid);
+    listener.assert_not_triggered(fire_uid);
+    listener.assert_triggered(menu_uid, 1);
+    listener.proxy().enable_group("game").unwrap();
+    listener.play(&script);
+    listener.assert_triggered(jump_uid, 1);
+    listener.assert_triggered(fire_uid, 1);
+    listener.assert_triggered(menu_uid, 2);
+}
+
+#[test]
+fn resuming_keeps_bindings_disabled_on_their_own() {
+    let mut listener = ScriptedListener::new();
+    let a_uid = bind_with(&mut listener, "A", BindingOptions::default());
+    let b_uid = bind_with(&mut listener, "B", BindingOptions::default());
+    let script = Script::new().tap(KeyCode::KeyA).tap(KeyCode::KeyB);
+    listener.proxy().disable(b_uid).unwrap();
+    listener.proxy().pause().unwrap();
+    listener.play(&script);
+    listener.assert_not_triggered(a_uid);
+    listener.proxy().resume().unwrap();
+    listener.play(&script);
+    listener.assert_triggered(a_uid, 1);
+    listener.assert_not_triggered(b_uid);
+}
+
+#[test]
+fn disabled_dual_role_key_is_a_plain_key() {
+    let mut listener = ScriptedListener::new();
+    let tap_uid = listener
+        .proxy()
+        .bind_dual_role(KeyCode::Space, KeyCode::ControlLeft, Box::new(|| {}))
+        .unwrap()
+        .detach();
+    let copy_uid = bind_with(&mut listener, "Ctrl+C", BindingOptions::default());
+    listener.play(&Script::new().down(KeyCode::Space));
+    listener.proxy().disable(tap_uid).unwrap();
+    listener.play(
+        &Script::new()
+            .tap(KeyCode::KeyC)
+            .up(KeyCode::Space)
+            .tap(KeyCode::Space),
+    );
+    listener.assert_not_triggered(tap_uid);
+    listener.assert_not_triggered(copy_uid);
+}
+
+#[test]
+fn keymap_bindings_match_only_while_pushed() {
+    let mut listener = ScriptedListener::new();
+    let uid = bind_with(
+        &mut listener,
+        "Ctrl+S",
+        BindingOptions {
+            keymap: Some("editor".to_string()),
+            ..Default::default()
+        },
+    );
+    let script = Script::new().chord(&[KeyCode::ControlLeft], KeyCode::KeyS);
+    listener.play(&script);
+    listener.assert_not_triggered(uid);
+    listener.proxy().push_keymap("editor").unwrap();
+    listener.play(&script);
+    listener.assert_triggered(uid, 1);
+    listener.proxy().pop_keymap("editor").unwrap();
+    listener.play(&script);
+    listener.assert_triggered(uid, 1);
+}
+
+#[test]
+fn upper_keymap_shadows_the_same_keys_below() {
+    let mut listener = ScriptedListener::new();
+    let global_escape_uid = bind_with(&mut listener, "Escape", BindingOptions::default());
+    let global_save_uid = bind_with(&mut listener, "Ctrl+S", BindingOptions::default());
+    let dialog_escape_uid = bind_with(
+        &mut listener,
+        "Escape",
+        BindingOptions {
+            keymap: Some("modal-dialog".to_string()),
+            ..Default::default()
+        },
+    );
+    let script = Script::new()
+        .tap(KeyCode::Escape)
+        .chord(&[KeyCode::ControlLeft], KeyCode::KeyS);
+    listener.proxy().push_keymap("modal-dialog").unwrap();
+    listener.play(&script);
+    listener.assert_not_triggered(global_escape_uid);
+    listener.assert_triggered(dialog_escape_uid, 1);
+    // keys without a binding in the dialog fall through
+    listener.assert_triggered(global_save_uid, 1);
+
+    listener.proxy().pop_keymap("modal-dialog").unwrap();
+    listener.play(&script);
+    listener.assert_triggered(global_escape_uid, 1);
+    listener.assert_triggered(dialog_escape_uid, 1);
+}
+
+#[test]
+fn upper_keymap_shadows_longer_sequences_below() {
+    let mut listener = ScriptedListener::new();
+    let normal_uid = bind_with(
+        &mut listener,
+        "G, G",
+        BindingOptions {
+            keymap: Some("normal".to_string()),
+            ..Default::default()
+        },
+    );
+    let insert_uid = bind_with(
+        &mut listener,
+        "G",
+        BindingOptions {
+            keymap: Some("insert".to_string()),
+            ..Default::default()
+        },
+    );
+    listener.proxy().push_keymap("normal").unwrap();
+    listener.proxy().push_keymap("insert").unwrap();
+    listener.play(&Script::new().tap(KeyCode::KeyG).tap(KeyCode::KeyG));
+    listener.assert_not_triggered(normal_uid);
+    listener.assert_triggered(insert_uid, 2);
+    listener.proxy().pop_keymap("insert").unwrap();
+    listener.play(&Script::new().tap(KeyCode::KeyG).tap(KeyCode::KeyG));
+    listener.assert_triggered(normal_uid, 1);
+    listener.assert_triggered(insert_uid, 2);
+}
+
+#[test]
+fn popping_a_keymap_drops_its_partial_sequences() {
+    let mut listener = ScriptedListener::new();
+    let uid = bind_with(
+        &mut listener,
+        "G, G",
+        BindingOptions {
+            keymap: Some("normal".to_string()),
+            ..Default::default()
+        },
+    );
+    listener.proxy().push_keymap("normal").unwrap();
+    listener.play(&Script::new().tap(KeyCode::KeyG));
+    listener.proxy().pop_keymap("normal").unwrap();
+    listener.proxy().push_keymap("normal").unwrap();
+    listener.play(&Script::new().tap(KeyCode::KeyG));
+    listener.assert_not_triggered(uid);
+}
+
+#[test]
+fn popping_a_keymap_below_the_top_keeps_the_top() {
+    let mut listener = ScriptedListener::new();
+    let editor_uid = bind_with(
+        &mut listener,
+        "Escape",
+        BindingOptions {
+            keymap: Some("editor".to_string()),
+            ..Default::default()
+        },
+    );
+    let dialog_uid = bind_with(
+        &mut listener,
+        "Escape",
+        BindingOptions {
+            keymap: Some("modal-dialog".to_string()),
+            ..Default::default()
+        },
+    );
+    listener.proxy().push_keymap("editor").unwrap();
+    listener.proxy().push_keymap("modal-dialog").unwrap();
+    listener.proxy().pop_keymap("editor").unwrap();
+    listener.play(&Script::new().tap(KeyCode::Escape));
+    listener.assert_not_triggered(editor_uid);
+    listener.assert_triggered(dialog_uid, 1);
+    listener.proxy().pop_keymap("modal-dialog").unwrap();
+    listener.play(&Script::new().tap(KeyCode::Escape));
+    listener.assert_triggered(dialog_uid, 1);
+}
+
+/// holds Ctrl+K between "Ctrl+K" and "Ctrl+K, Ctrl+C" of `group`, then calls `stop`,
+/// which must drop the waiting binding without triggering it
+fn assert_stopping_drops_the_pending_match(stop: fn(&mut ListenerProxy)) {
+    // the bindings are cleared in hash order, so try a few orders
+    for _ in 0..20 {
+        let mut listener = ScriptedListener::new();
+        let short_uid = bind_with(
+            &mut listener,
+            "Ctrl+K",
+            BindingOptions {
+                group: Some("editor".to_string()),
+                ..Default::default()
+            },
+        );
+        let long_uid = bind_with(
+            &mut listener,
+            "Ctrl+K, Ctrl+C",
+            BindingOptions {
+                group: Some("editor".to_string()),
+                ..Default::default()
+            },
+        );
+        listener.play(&Script::new().down(KeyCode::ControlLeft).tap(KeyCode::KeyK));
+        assert!(listener.proxy().is_waiting_for_next_key());
+        stop(listener.proxy());
+        listener.play(&Script::new().wait(10_000));
+        listener.play(&Script::new().tap(KeyCode::KeyX).up(KeyCode::ControlLeft));
+        listener.assert_not_triggered(short_uid);
+        listener.assert_not_triggered(long_uid);
+        assert!(!listener.proxy().is_waiting_for_next_key());
+    }
+}
+
+#[test]
+fn pausing_never_triggers_the_pending_match() {
+    assert_stopping_drops_the_pending_match(|proxy| proxy.pause().unwrap());
+}
+
+#[test]
+fn disabling_a_group_never_triggers_the_pending_match() {
+    assert_stopping_drops_the_pending_match(|proxy| proxy.disable_group("editor").unwrap());
+}
+
+#[test]
+fn popping_a_keymap_never_triggers_its_pending_match() {
+    for _ in 0..20 {
+        let mut listener = ScriptedListener::new();
+        let short_uid = bind_with(
+            &mut listener,
+            "Ctrl+K",
+            BindingOptions {
+                keymap: Some("editor".to_string()),
+                ..Default::default()
+            },
+        );
+        let long_uid = bind_with(
+            &mut listener,
+            "Ctrl+K, Ctrl+C",
+            BindingOptions {
+                keymap: Some("editor".to_string()),
+                ..Default::default()
+            },
+        );
+        listener.proxy().push_keymap("editor").unwrap();
+        listener.play(&Script::new().down(KeyCode::ControlLeft).tap(KeyCode::KeyK));
+        listener.proxy().pop_keymap("editor").unwrap();
+        listener.play(&Script::new().wait(10_000));
+        listener.play(&Script::new().tap(KeyCode::KeyX).up(KeyCode::ControlLeft));
+        listener.assert_not_triggered(short_uid);
+        listener.assert_not_triggered(long_uid);
+    }
+}
+
+#[test]
+fn pushing_a_keymap_drops_the_sequences_it_shadows() {
+    let mut listener = ScriptedListener::new();
+    let global_uid = bind_with(&mut listener, "A, B", BindingOptions::default());
+    let other_uid = bind_with(&mut listener, "A, C", BindingOptions::default());
+    let dialog_uid = bind_with(
+        &mut listener,
+        "B, D",
+        BindingOptions {
+            keymap: Some("modal-dialog".to_string()),
+            ..Default::default()
+        },
+    );
+    listener.play(&Script::new().tap(KeyCode::KeyA));
+    listener.proxy().push_keymap("modal-dialog").unwrap();
+    listener.play(&Script::new().tap(KeyCode::KeyB).tap(KeyCode::KeyD));
+    listener.assert_not_triggered(global_uid);
+    listener.assert_triggered(dialog_uid, 1);
+
+    // sequences of keys the new keymap doesn't bind go on
+    listener.proxy().pop_keymap("modal-dialog").unwrap();
+    listener.play(&Script::new().tap(KeyCode::KeyA));
+    listener.proxy().push_keymap("modal-dialog").unwrap();
+    listener.play(&Script::new().tap(KeyCode::KeyC));
+    listener.assert_triggered(other_uid, 1);
+}
+
+#[test]
+fn pushing_a_keymap_mid_ambiguity_settles_on_the_shorter_binding() {
+    let mut listener = ScriptedListener::new();
+    let short_uid = bind_with(&mut listener, "Ctrl+K", BindingOptions::default());
+    let long_uid = bind_with(&mut listener, "Ctrl+K, Ctrl+C", BindingOptions::default());
+    let dialog_uid = bind_with(
+        &mut listener,
+        "Ctrl+C",
+        BindingOptions {
+            keymap: Some("modal-dialog".to_string()),
+            ..Default::default()
+        },
+    );
+    listener.play(&Script::new().down(KeyCode::ControlLeft).tap(KeyCode::KeyK));
+    listener.proxy().push_keymap("modal-dialog").unwrap();
+    listener.play(&Script::new().tap(KeyCode::KeyC).up(KeyCode::ControlLeft));
+    listener.assert_not_triggered(long_uid);
+    listener.assert_triggered(short_uid, 1);
+    listener.assert_triggered(dialog_uid, 1);
+}
@@ -0,0 +1,90 @@
+use inbot::*;
+
+#[test]
+fn parses_sequence_with_aliases() {
+    let binding_keys = parse_sequence("Ctrl+Shift+K, control + SHIFT + c").unwrap();
+    let modifer_keys = vec![KeyCode::Control, KeyCode::Shift];
+    assert_eq!(
+        binding_keys,
+        vec![
+            BindingKey {
+                key: KeyCode::KeyK,
+                modifer_keys: modifer_keys.clone(),
+            },
+            BindingKey {
+                key: KeyCode::KeyC,
+                modifer_keys,
+            },
+        ]
+    );
+    for (name, key) in [
+        ("Cmd+A", KeyCode::Meta),
+        ("Win+A", KeyCode::Meta),
+        ("super+A", KeyCode::Meta),
+        ("Option+A", KeyCode::Alt),
+        ("LCtrl+A", KeyCode::ControlLeft),
+        ("RCtrl+A", KeyCode::ControlRight),
+        ("AltGr+A", KeyCode::AltRight),
+    ] {
+        let binding_key: BindingKey = name.parse().unwrap();
+        assert_eq!(binding_key.modifer_keys, vec![key], "{}", name);
+    }
+}
+
+#[test]
+fn parses_symbols_digits_and_variant_names() {
+    assert_eq!("/".parse::<KeyCode>().unwrap(), KeyCode::Slash);
+    assert_eq!("7".parse::<KeyCode>().unwrap(), KeyCode::Num7);
+    assert_eq!("pgdn".parse::<KeyCode>().unwrap(), KeyCode::PageDown);
+    assert_eq!("MouseLeft".parse::<KeyCode>().unwrap(), KeyCode::MouseLeft);
+    assert_eq!("keyq".parse::<KeyCode>().unwrap(), KeyCode::KeyQ);
+    assert_eq!("Mouse4".parse::<KeyCode>().unwrap(), KeyCode::MouseX1);
+    assert_eq!(
+        "X2+ScrollDown".parse::<BindingKey>().unwrap(),
+        BindingKey {
+            key: KeyCode::WheelDown,
+            modifer_keys: vec![KeyCode::MouseX2],
+        }
+    );
+}
+
+#[test]
+fn errors_point_at_the_bad_token() {
+    let error = parse_sequence("Ctrl+K, Ctlr+C").unwrap_err();
+    assert_eq!(error.kind(), &ParseErrorKind::UnknownKey);
+    assert_eq!(error.token(), "Ctlr");
+    assert_eq!(error.position(), 8);
+    assert_eq!(
+        error.to_string(),
+        "unknown key `Ctlr` at position 8 in `Ctrl+K, Ctlr+C`"
+    );
+
+    let error = parse_sequence("Ctrl++K").unwrap_err();
+    assert_eq!(error.kind(), &ParseErrorKind::EmptyToken);
+    assert_eq!(error.position(), 5);
+
+    let error = parse_sequence("Ctrl+K,").unwrap_err();
+    assert_eq!(error.kind(), &ParseErrorKind::EmptyToken);
+    assert_eq!(error.position(), 7);
+
+    let error = "Shift+Ctrl+shift+K".parse::<BindingKey>().unwrap_err();
+    assert_eq!(error.kind(), &ParseErrorKind::DuplicateKey);
+    assert_eq!(error.token(), "shift");
+    assert_eq!(error.position(), 11);
+}
+
+#[test]
+fn formatting_round_trips() {
+    for input in [
+        "Ctrl+Shift+K, Ctrl+Shift+C",
+        "Alt+F4",
+        "Meta+RShift+Left",
+        "LCtrl+LShift+LAlt+LMeta+Space",
+        "Esc, `, 1, -, =, [, ], \\, ;, ', ., /, Comma, Space",
+        "PageUp, PageDown, Delete, Insert, MouseMiddle",
+    ] {
+        let binding_keys = parse_sequence(input).unwrap();
+        assert_eq!(format_sequence(&binding_keys), input);
+    }
+    assert_eq!(KeyCode::Unknown(0xFF).to_name(), "Unknown(255)");
+}
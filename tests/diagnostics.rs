@@ -0,0 +1,78 @@
+#![cfg(feature = "tracing")]
+
+use inbot::*;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Level, Metadata, Subscriber};
+
+/// counts the warnings
+struct WarnCounter(Arc<AtomicUsize>);
+
+impl Subscriber for WarnCounter {
+    fn enabled(&self, _: &Metadata<'_>) -> bool {
+        true
+    }
+
+    fn new_span(&self, _: &Attributes<'_>) -> Id {
+        Id::from_u64(1)
+    }
+
+    fn record(&self, _: &Id, _: &Record<'_>) {}
+
+    fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        if *event.metadata().level() == Level::WARN {
+            self.0.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    fn enter(&self, _: &Id) {}
+
+    fn exit(&self, _: &Id) {}
+}
+
+/// disables the warnings
+struct NoWarnings;
+
+impl Subscriber for NoWarnings {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        *metadata.level() != Level::WARN
+    }
+
+    fn new_span(&self, _: &Attributes<'_>) -> Id {
+        Id::from_u64(1)
+    }
+
+    fn record(&self, _: &Id, _: &Record<'_>) {}
+
+    fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+    fn event(&self, _: &Event<'_>) {}
+
+    fn enter(&self, _: &Id) {}
+
+    fn exit(&self, _: &Id) {}
+}
+
+fn bind_dual_role_repeatedly() {
+    let mut listener = ScriptedListener::new();
+    for _ in 0..50 {
+        listener
+            .proxy()
+            .bind_dual_role(KeyCode::Space, KeyCode::ControlLeft, Box::new(|| {}))
+            .unwrap()
+            .detach();
+    }
+    listener.play(&Script::new().tap(KeyCode::Space));
+}
+
+#[test]
+fn repeated_warnings_are_rate_limited() {
+    // disabled warnings don't use up the budget of the enabled ones
+    tracing::subscriber::with_default(NoWarnings, bind_dual_role_repeatedly);
+    let warnings = Arc::new(AtomicUsize::new(0));
+    tracing::subscriber::with_default(WarnCounter(warnings.clone()), bind_dual_role_repeatedly);
+    assert_eq!(warnings.load(Ordering::Relaxed), 10);
+}
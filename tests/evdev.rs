@@ -0,0 +1,277 @@
+#![cfg(target_os = "linux")]
+
+use inbot::*;
+use std::path::PathBuf;
+
+const EV_SYN: u16 = 0x00;
+const EV_KEY: u16 = 0x01;
+const EV_REL: u16 = 0x02;
+const EV_MSC: u16 = 0x04;
+const SYN_REPORT: u16 = 0x00;
+const SYN_DROPPED: u16 = 0x03;
+const REL_WHEEL: u16 = 0x08;
+
+struct Recording {
+    bytes: Vec<u8>,
+    millis: u64,
+}
+
+impl Recording {
+    fn new() -> Self {
+        Self {
+            bytes: Vec::new(),
+            millis: 1_000,
+        }
+    }
+
+    // the `input_event` layout of 64-bit Linux
+    fn event(mut self, type_: u16, code: u16, value: i32) -> Self {
+        let sec = (self.millis / 1000) as i64;
+        let usec = (self.millis % 1000 * 1000) as i64;
+        self.bytes.extend_from_slice(&sec.to_ne_bytes());
+        self.bytes.extend_from_slice(&usec.to_ne_bytes());
+        self.bytes.extend_from_slice(&type_.to_ne_bytes());
+        self.bytes.extend_from_slice(&code.to_ne_bytes());
+        self.bytes.extend_from_slice(&value.to_ne_bytes());
+        self
+    }
+
+    // a key event the way the kernel reports it, with its scan code and a sync report
+    fn key(self, key: KeyCode, value: i32) -> Self {
+        let mut recording = self
+            .event(EV_MSC, 4, 0x1d)
+            .event(EV_KEY, key.to_evdev_code() as u16, value)
+            .event(EV_SYN, 0, 0);
+        recording.millis += 10;
+        recording
+    }
+
+    fn tap(self, key: KeyCode) -> Self {
+        self.key(key, 1).key(key, 0)
+    }
+}
+
+fn temp_path(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("inbot-{}-{}", std::process::id(), name));
+    let _ = std::fs::remove_dir_all(&path);
+    let _ = std::fs::remove_file(&path);
+    path
+}
+
+fn poll_all(backend: &mut dyn InputBackend) -> Vec<InputKey> {
+    let mut input_keys = Vec::new();
+    backend
+        .poll(&mut |input_key| {
+            input_keys.push(input_key);
+            InputDecision::Pass
+        })
+        .unwrap();
+    input_keys
+}
+
+fn replay(listener: &mut ScriptedListener, input_keys: &[InputKey]) {
+    let script = input_keys
+        .iter()
+        .fold(Script::new(), |script, input_key| script.push(*input_key));
+    listener.play(&script);
+}
+
+#[test]
+fn recording_is_translated_into_input_keys() {
+    let path = temp_path("recording");
+    let recording = Recording::new()
+        .key(KeyCode::ControlLeft, 1)
+        .key(KeyCode::ControlLeft, 2)
+        .tap(KeyCode::KeyF)
+        .key(KeyCode::ControlLeft, 0);
+    std::fs::write(&path, &recording.bytes).unwrap();
+
+    let mut backend = EvdevBackend::from_recording(&path).unwrap();
+    backend.install().unwrap();
+    let input_keys = poll_all(&mut backend);
+    backend.uninstall();
+    std::fs::remove_file(&path).unwrap();
+
+    let expected = [
+        (KeyCode::ControlLeft, KeyOpt::Down, 1_000),
+        (KeyCode::ControlLeft, KeyOpt::Down, 1_010),
+        (KeyCode::KeyF, KeyOpt::Down, 1_020),
+        (KeyCode::KeyF, KeyOpt::Up, 1_030),
+        (KeyCode::ControlLeft, KeyOpt::Up, 1_040),
+    ];
+    assert_eq!(input_keys.len(), expected.len());
+    for (input_key, (key, opt, time)) in input_keys.iter().zip(expected) {
+        assert!(input_key.key == key, "{}", input_key);
+        assert!(input_key.opt == opt, "{}", input_key);
+        assert_eq!(input_key.time, time);
+    }
+}
+
+#[test]
+fn wheel_notches_are_reported_as_taps() {
+    let path = temp_path("wheel");
+    let recording = Recording::new()
+        .key(KeyCode::MouseX1, 1)
+        .event(EV_REL, REL_WHEEL, -2)
+        .event(EV_SYN, 0, 0)
+        .key(KeyCode::MouseX1, 0);
+    std::fs::write(&path, &recording.bytes).unwrap();
+
+    let mut backend = EvdevBackend::from_recording(&path).unwrap();
+    backend.install().unwrap();
+    let input_keys = poll_all(&mut backend);
+    backend.uninstall();
+    std::fs::remove_file(&path).unwrap();
+
+    let keys: Vec<(KeyCode, KeyOpt)> = input_keys
+        .iter()
+        .map(|input_key| (input_key.key, input_key.opt))
+        .collect();
+    assert_eq!(
+        keys,
+        vec![
+            (KeyCode::MouseX1, KeyOpt::Down),
+            (KeyCode::WheelDown, KeyOpt::Down),
+            (KeyCode::WheelDown, KeyOpt::Up),
+            (KeyCode::MouseX1, KeyOpt::Up),
+        ]
+    );
+    assert_eq!(input_keys[1].wheel_delta, 2 * WHEEL_NOTCH);
+}
+
+#[test]
+fn keys_are_resynced_after_dropped_events() {
+    let path = temp_path("dropped");
+    let recording = Recording::new()
+        .key(KeyCode::ControlLeft, 1)
+        .key(KeyCode::ShiftLeft, 1)
+        .event(EV_SYN, SYN_DROPPED, 0)
+        .event(EV_KEY, KeyCode::KeyF.to_evdev_code() as u16, 1)
+        .event(EV_SYN, SYN_REPORT, 0)
+        .tap(KeyCode::KeyA);
+    std::fs::write(&path, &recording.bytes).unwrap();
+
+    let mut backend = EvdevBackend::from_recording(&path).unwrap();
+    backend.install().unwrap();
+    let input_keys = poll_all(&mut backend);
+    backend.uninstall();
+    std::fs::remove_file(&path).unwrap();
+
+    // a recording has no key state to query, so the held keys are released
+    let expected = [
+        (KeyCode::ControlLeft, KeyOpt::Down, 1_000),
+        (KeyCode::ShiftLeft, KeyOpt::Down, 1_010),
+        (KeyCode::ControlLeft, KeyOpt::Up, 1_020),
+        (KeyCode::ShiftLeft, KeyOpt::Up, 1_020),
+        (KeyCode::KeyA, KeyOpt::Down, 1_020),
+        (KeyCode::KeyA, KeyOpt::Up, 1_030),
+    ];
+    assert_eq!(input_keys.len(), expected.len());
+    for (input_key, (key, opt, time)) in input_keys.iter().zip(expected) {
+        assert!(input_key.key == key, "{}", input_key);
+        assert!(input_key.opt == opt, "{}", input_key);
+        assert_eq!(input_key.time, time);
+    }
+}
+
+#[test]
+fn recording_drives_bindings() {
+    let path = temp_path("bindings");
+    let recording = Recording::new()
+        .key(KeyCode::ControlLeft, 1)
+        .key(KeyCode::ShiftLeft, 1)
+        .tap(KeyCode::KeyK)
+        .tap(KeyCode::KeyC)
+        .key(KeyCode::ShiftLeft, 0)
+        .key(KeyCode::ControlLeft, 0);
+    std::fs::write(&path, &recording.bytes).unwrap();
+
+    let mut listener = ScriptedListener::new();
+    let modifer_keys = vec![KeyCode::ControlLeft, KeyCode::ShiftLeft];
+    let uid = listener
+        .proxy()
+        .bind_multi(
+            vec![
+                BindingKey {
+                    key: KeyCode::KeyK,
+                    modifer_keys: modifer_keys.clone(),
+                },
+                BindingKey {
+                    key: KeyCode::KeyC,
+                    modifer_keys,
+                },
+            ],
+            Box::new(|| {}),
+        )
+        .unwrap()
+        .detach();
+    let mut backend = EvdevBackend::from_recording(&path).unwrap();
+    backend.install().unwrap();
+    replay(&mut listener, &poll_all(&mut backend));
+    std::fs::remove_file(&path).unwrap();
+    listener.assert_triggered(uid, 1);
+}
+
+#[test]
+fn devices_dir_is_rescanned_for_new_devices() {
+    let dir = temp_path("devices");
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(
+        dir.join("event0"),
+        Recording::new().tap(KeyCode::KeyA).bytes,
+    )
+    .unwrap();
+    std::fs::write(
+        dir.join("mouse0"),
+        Recording::new().tap(KeyCode::KeyZ).bytes,
+    )
+    .unwrap();
+
+    let mut backend = EvdevBackend::with_devices_dir(&dir);
+    backend.set_rescan_interval(std::time::Duration::ZERO);
+    backend.install().unwrap();
+    let input_keys = poll_all(&mut backend);
+    assert_eq!(input_keys.len(), 2);
+    assert!(input_keys
+        .iter()
+        .all(|input_key| input_key.key == KeyCode::KeyA));
+
+    std::fs::write(
+        dir.join("event1"),
+        Recording::new().tap(KeyCode::KeyB).bytes,
+    )
+    .unwrap();
+    let input_keys = poll_all(&mut backend);
+    assert_eq!(input_keys.len(), 2);
+    assert!(input_keys
+        .iter()
+        .all(|input_key| input_key.key == KeyCode::KeyB));
+    assert!(poll_all(&mut backend).is_empty());
+
+    backend.uninstall();
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn devices_that_failed_to_open_are_retried() {
+    let dir = temp_path("retried");
+    std::fs::create_dir_all(&dir).unwrap();
+    // the node is there before it can be opened, as with udev setting the ACL late
+    let target = dir.join("keyboard");
+    std::os::unix::fs::symlink(&target, dir.join("event0")).unwrap();
+
+    let mut backend = EvdevBackend::with_devices_dir(&dir);
+    backend.set_rescan_interval(std::time::Duration::ZERO);
+    backend.install().unwrap();
+    assert!(poll_all(&mut backend).is_empty());
+
+    std::fs::write(&target, Recording::new().tap(KeyCode::KeyA).bytes).unwrap();
+    let input_keys = poll_all(&mut backend);
+    assert_eq!(input_keys.len(), 2);
+    assert!(input_keys
+        .iter()
+        .all(|input_key| input_key.key == KeyCode::KeyA));
+
+    backend.uninstall();
+    std::fs::remove_dir_all(&dir).unwrap();
+}
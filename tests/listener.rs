@@ -0,0 +1,259 @@
+use inbot::*;
+use std::io;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+struct FailingBackend;
+
+impl InputBackend for FailingBackend {
+    fn install(&mut self) -> io::Result<()> {
+        Err(io::Error::new(io::ErrorKind::PermissionDenied, "no access"))
+    }
+
+    fn poll(&mut self, _: &mut dyn FnMut(InputKey) -> InputDecision) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn now(&self) -> u32 {
+        0
+    }
+
+    fn uninstall(&mut self) {}
+}
+
+/// serializes the tests of the global listener thread
+static GLOBAL_LISTENER: Mutex<()> = Mutex::new(());
+
+#[test]
+fn failing_install_is_reported_by_start_listen() {
+    let _global_listener = GLOBAL_LISTENER.lock().unwrap_or_else(|e| e.into_inner());
+    match start_listen_with(Box::new(FailingBackend)) {
+        Err(Error::HookInstall(e)) => assert_eq!(e.kind(), io::ErrorKind::PermissionDenied),
+        Err(e) => panic!("unexpected error {}", e),
+        Ok(_) => panic!("started with a failing backend"),
+    }
+    assert!(matches!(stop_listen(), Err(Error::ListenerNotRunning)));
+}
+
+#[test]
+fn listener_restarts_after_stop_listen() {
+    let _global_listener = GLOBAL_LISTENER.lock().unwrap_or_else(|e| e.into_inner());
+    let mut stale_proxy = start_listen_with(Box::new(ScriptedBackend::new())).unwrap();
+    stop_listen().unwrap();
+    assert!(matches!(
+        stale_proxy.bind_multi(parse_sequence("A").unwrap(), Box::new(|| {})),
+        Err(Error::ListenerNotRunning)
+    ));
+
+    let backend = ScriptedBackend::new();
+    let input = backend.get_input();
+    let mut proxy = start_listen_with(Box::new(backend)).unwrap();
+    let triggered = Arc::new(AtomicUsize::new(0));
+    let triggered_copy = triggered.clone();
+    let _binding = proxy
+        .bind_multi(
+            parse_sequence("A").unwrap(),
+            Box::new(move || {
+                triggered_copy.fetch_add(1, Ordering::SeqCst);
+            }),
+        )
+        .unwrap();
+    // the listener may poll the first taps before it handles the binding
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while triggered.load(Ordering::SeqCst) == 0 && Instant::now() < deadline {
+        input.feed(&Script::new().tap(KeyCode::KeyA));
+        std::thread::sleep(Duration::from_millis(10));
+        proxy.update().unwrap();
+    }
+    assert!(triggered.load(Ordering::SeqCst) > 0);
+    stop_listen().unwrap();
+    assert!(matches!(stop_listen(), Err(Error::ListenerNotRunning)));
+}
+
+/// installs, but can't hold input back, like the evdev backend
+struct PassingBackend;
+
+impl InputBackend for PassingBackend {
+    fn install(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn poll(&mut self, _: &mut dyn FnMut(InputKey) -> InputDecision) -> io::Result<()> {
+        std::thread::sleep(Duration::from_millis(1));
+        Ok(())
+    }
+
+    fn now(&self) -> u32 {
+        0
+    }
+
+    fn uninstall(&mut self) {}
+}
+
+#[test]
+fn consuming_needs_a_backend_that_can_consume() {
+    let _global_listener = GLOBAL_LISTENER.lock().unwrap_or_else(|e| e.into_inner());
+    let mut proxy = start_listen_with(Box::new(PassingBackend)).unwrap();
+    let options = BindingOptions {
+        consume: true,
+        ..Default::default()
+    };
+    let result = proxy.bind_multi_with(parse_sequence("A").unwrap(), options, Box::new(|| {}));
+    assert!(matches!(result, Err(Error::ConsumeUnsupported)));
+    let result = proxy.remap("CapsLock".parse().unwrap(), "LCtrl".parse().unwrap());
+    assert!(matches!(result, Err(Error::ConsumeUnsupported)));
+    // bindings that let the keys through still work
+    let _binding = proxy
+        .bind_multi(parse_sequence("A").unwrap(), Box::new(|| {}))
+        .unwrap();
+    stop_listen().unwrap();
+}
+
+#[test]
+fn invalid_bindings_are_rejected_when_bound() {
+    let mut listener = ScriptedListener::new();
+    let result = listener.proxy().bind_multi(Vec::new(), Box::new(|| {}));
+    assert!(matches!(result, Err(Error::InvalidBinding(_))));
+
+    let options = BindingOptions {
+        dual_role: Some(DualRole::new(KeyCode::ControlLeft)),
+        ..Default::default()
+    };
+    let result = listener.proxy().bind_multi_with(
+        parse_sequence("Shift+Space").unwrap(),
+        options,
+        Box::new(|| {}),
+    );
+    assert!(matches!(result, Err(Error::InvalidBinding(_))));
+}
+
+#[test]
+fn unbinding_stops_the_callbacks() {
+    let mut listener = ScriptedListener::new();
+    let uid = listener
+        .proxy()
+        .bind_multi(parse_sequence("A").unwrap(), Box::new(|| {}))
+        .unwrap()
+        .detach();
+    listener.play(&Script::new().tap(KeyCode::KeyA));
+    listener.proxy().unbind(uid).unwrap();
+    listener.play(&Script::new().tap(KeyCode::KeyA));
+    listener.assert_triggered(uid, 1);
+    listener.assert_no_bindings();
+    assert!(matches!(
+        listener.proxy().unbind(uid),
+        Err(Error::UnknownBinding(unknown_uid)) if unknown_uid == uid
+    ));
+    assert!(matches!(
+        listener.proxy().unremap(uid),
+        Err(Error::UnknownBinding(_))
+    ));
+}
+
+#[test]
+fn conflicting_dual_role_is_reported_by_update() {
+    let mut listener = ScriptedListener::new();
+    let uid = listener
+        .proxy()
+        .bind_dual_role(KeyCode::Space, KeyCode::ControlLeft, Box::new(|| {}))
+        .unwrap()
+        .detach();
+    let conflicting_uid = listener
+        .proxy()
+        .bind_dual_role(KeyCode::Space, KeyCode::ShiftLeft, Box::new(|| {}))
+        .unwrap()
+        .detach();
+    listener.play(&Script::new().tap(KeyCode::Space));
+    let errors = listener.take_errors();
+    assert_eq!(errors.len(), 1);
+    assert!(matches!(
+        errors[0],
+        Error::DuplicateBinding(rejected_uid) if rejected_uid == conflicting_uid
+    ));
+    listener.assert_triggered(uid, 1);
+    assert!(matches!(
+        listener.proxy().unbind(conflicting_uid),
+        Err(Error::UnknownBinding(_))
+    ));
+}
+
+#[test]
+fn dropping_the_handle_unbinds() {
+    let mut listener = ScriptedListener::new();
+    let handle = listener
+        .proxy()
+        .bind_multi(parse_sequence("A, B").unwrap(), Box::new(|| {}))
+        .unwrap();
+    let uid = handle.uid();
+    listener.play(&Script::new().tap(KeyCode::KeyA).tap(KeyCode::KeyB));
+    assert_eq!(listener.get_binding_count(), 1);
+    // drop the binding half way through the sequence
+    listener.play(&Script::new().tap(KeyCode::KeyA));
+    drop(handle);
+    listener.play(&Script::new().tap(KeyCode::KeyB));
+    listener.assert_triggered(uid, 1);
+    listener.assert_no_bindings();
+    assert!(matches!(
+        listener.proxy().unbind(uid),
+        Err(Error::UnknownBinding(_))
+    ));
+}
+
+#[test]
+fn rebinding_replaces_the_old_binding() {
+    let mut listener = ScriptedListener::new();
+    let mut handle = listener
+        .proxy()
+        .bind_multi(parse_sequence("Ctrl+F").unwrap(), Box::new(|| {}))
+        .unwrap();
+    let old_uid = handle.uid();
+    handle = listener
+        .proxy()
+        .bind_multi(parse_sequence("Ctrl+G").unwrap(), Box::new(|| {}))
+        .unwrap();
+    let ctrl_f = Script::new()
+        .down(KeyCode::ControlLeft)
+        .tap(KeyCode::KeyF)
+        .up(KeyCode::ControlLeft);
+    let ctrl_g = Script::new()
+        .down(KeyCode::ControlLeft)
+        .tap(KeyCode::KeyG)
+        .up(KeyCode::ControlLeft);
+    listener.play(&ctrl_f);
+    listener.play(&ctrl_g);
+    listener.assert_not_triggered(old_uid);
+    listener.assert_triggered(handle.uid(), 1);
+    assert_eq!(listener.get_binding_count(), 1);
+}
+
+#[test]
+fn handles_report_whether_bindings_are_active() {
+    let mut listener = ScriptedListener::new();
+    let once = listener
+        .proxy()
+        .bind_once(parse_sequence("A").unwrap(), Box::new(|| {}))
+        .unwrap();
+    let dual_role = listener
+        .proxy()
+        .bind_dual_role(KeyCode::Space, KeyCode::ControlLeft, Box::new(|| {}))
+        .unwrap();
+    let conflicting = listener
+        .proxy()
+        .bind_dual_role(KeyCode::Space, KeyCode::ShiftLeft, Box::new(|| {}))
+        .unwrap();
+    assert!(once.is_active() && dual_role.is_active());
+    listener.play(&Script::new().tap(KeyCode::KeyA));
+    assert!(!once.is_active());
+    assert!(!conflicting.is_active());
+    assert!(dual_role.is_active());
+    assert!(matches!(
+        conflicting.unbind(),
+        Err(Error::UnknownBinding(_))
+    ));
+
+    dual_role.unbind().unwrap();
+    listener.play(&Script::new().tap(KeyCode::KeyA));
+    listener.assert_triggered(once.uid(), 1);
+    listener.assert_no_bindings();
+}
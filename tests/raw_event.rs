@@ -0,0 +1,114 @@
+use inbot::*;
+use std::collections::HashSet;
+
+fn keys_of(raw_events: &[RawEvent]) -> Vec<(KeyCode, KeyOpt)> {
+    raw_events
+        .iter()
+        .map(|raw_event| (raw_event.input_key.key, raw_event.input_key.opt))
+        .collect()
+}
+
+#[test]
+fn raw_events_carry_time_device_and_modifers() {
+    let mut listener = ScriptedListener::new();
+    let subscription = listener
+        .proxy()
+        .subscribe_raw(RawEventFilter::default())
+        .unwrap();
+    let script = Script::new()
+        .down(KeyCode::ShiftLeft)
+        .wait(10)
+        .tap(KeyCode::MouseLeft)
+        .up(KeyCode::ShiftLeft);
+    listener.play(&script);
+    let raw_events = subscription.drain();
+    assert_eq!(raw_events.len(), 4);
+    assert_eq!(raw_events[1].input_key.time, 10);
+    assert_eq!(raw_events[1].device, DeviceKind::Mouse);
+    assert_eq!(raw_events[1].modifer_keys, vec![KeyCode::ShiftLeft]);
+    assert_eq!(raw_events[0].device, DeviceKind::Keyboard);
+    assert!(raw_events[0].modifer_keys.is_empty());
+    assert!(raw_events[3].modifer_keys.is_empty());
+}
+
+#[test]
+fn raw_events_are_filtered() {
+    let mut listener = ScriptedListener::new();
+    let filter = RawEventFilter {
+        keys: Some(HashSet::from([KeyCode::KeyA, KeyCode::KeyB])),
+        opts: Some(vec![KeyOpt::Down]),
+        ..Default::default()
+    };
+    let subscription = listener.proxy().subscribe_raw(filter).unwrap();
+    let script = Script::new()
+        .tap(KeyCode::KeyA)
+        .tap(KeyCode::KeyC)
+        .tap(KeyCode::KeyB);
+    listener.play(&script);
+    assert_eq!(
+        keys_of(&subscription.drain()),
+        vec![(KeyCode::KeyA, KeyOpt::Down), (KeyCode::KeyB, KeyOpt::Down)]
+    );
+    assert!(subscription.try_recv().is_none());
+}
+
+#[test]
+fn full_buffer_drops_the_oldest_events() {
+    let mut listener = ScriptedListener::new();
+    let filter = RawEventFilter {
+        capacity: 2,
+        ..Default::default()
+    };
+    let subscription = listener.proxy().subscribe_raw(filter).unwrap();
+    let script = Script::new().tap(KeyCode::KeyA).tap(KeyCode::KeyB);
+    listener.play(&script);
+    assert_eq!(subscription.get_dropped_count(), 2);
+    assert_eq!(
+        keys_of(&subscription.drain()),
+        vec![(KeyCode::KeyB, KeyOpt::Down), (KeyCode::KeyB, KeyOpt::Up)]
+    );
+}
+
+#[test]
+fn raw_events_are_reported_before_remapping() {
+    let mut listener = ScriptedListener::new();
+    listener
+        .proxy()
+        .remap("CapsLock".parse().unwrap(), "LCtrl".parse().unwrap())
+        .unwrap();
+    let subscription = listener
+        .proxy()
+        .subscribe_raw(RawEventFilter::default())
+        .unwrap();
+    listener.play(&Script::new().tap(KeyCode::Capslock));
+    let raw_event = subscription
+        .recv_timeout(std::time::Duration::from_millis(100))
+        .unwrap();
+    assert_eq!(raw_event.input_key.key, KeyCode::Capslock);
+}
+
+#[test]
+fn mouse_events_carry_position_and_wheel_delta() {
+    let mut listener = ScriptedListener::new();
+    let subscription = listener
+        .proxy()
+        .subscribe_raw(RawEventFilter::default())
+        .unwrap();
+    let pos = CursorPos { x: 10, y: 20 };
+    listener.play(&Script::new().move_to(pos).wheel(KeyCode::WheelLeft));
+    let raw_events = subscription.drain();
+    assert_eq!(
+        keys_of(&raw_events),
+        vec![
+            (KeyCode::Unknown(0), KeyOpt::Move),
+            (KeyCode::WheelLeft, KeyOpt::Down),
+            (KeyCode::WheelLeft, KeyOpt::Up),
+        ]
+    );
+    assert!(raw_events
+        .iter()
+        .all(|raw_event| raw_event.device == DeviceKind::Mouse));
+    assert_eq!(raw_events[0].input_key.pos, Some(pos));
+    assert_eq!(raw_events[1].input_key.wheel_delta, WHEEL_NOTCH);
+    assert!(!raw_events[1].input_key.injected);
+}
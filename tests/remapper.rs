@@ -0,0 +1,130 @@
+use inbot::*;
+
+fn passed_keys(listener: &mut ScriptedListener) -> Vec<(KeyCode, KeyOpt)> {
+    listener
+        .take_passed_keys()
+        .into_iter()
+        .map(|input_key| (input_key.key, input_key.opt))
+        .collect()
+}
+
+fn remap(listener: &mut ScriptedListener, from: &str, to: &str) -> u32 {
+    listener
+        .proxy()
+        .remap(from.parse().unwrap(), to.parse().unwrap())
+        .unwrap()
+}
+
+#[test]
+fn remapped_key_emits_the_target_key() {
+    let mut listener = ScriptedListener::new();
+    remap(&mut listener, "CapsLock", "LCtrl");
+    let script = Script::new()
+        .down(KeyCode::Capslock)
+        .down(KeyCode::Capslock)
+        .tap(KeyCode::KeyC)
+        .up(KeyCode::Capslock);
+    listener.play(&script);
+    assert_eq!(
+        passed_keys(&mut listener),
+        vec![
+            (KeyCode::ControlLeft, KeyOpt::Down),
+            (KeyCode::ControlLeft, KeyOpt::Down),
+            (KeyCode::KeyC, KeyOpt::Down),
+            (KeyCode::KeyC, KeyOpt::Up),
+            (KeyCode::ControlLeft, KeyOpt::Up),
+        ]
+    );
+}
+
+#[test]
+fn bindings_see_the_remapped_keys() {
+    let mut listener = ScriptedListener::new();
+    remap(&mut listener, "CapsLock", "LCtrl");
+    let uid = listener
+        .proxy()
+        .bind_multi(parse_sequence("Ctrl+C").unwrap(), Box::new(|| {}))
+        .unwrap()
+        .detach();
+    listener.play(&Script::new().chord(&[KeyCode::Capslock], KeyCode::KeyC));
+    listener.assert_triggered(uid, 1);
+}
+
+#[test]
+fn source_modifers_are_released_while_remapped() {
+    let mut listener = ScriptedListener::new();
+    remap(&mut listener, "RAlt+J", "Left");
+    let script = Script::new()
+        .down(KeyCode::AltRight)
+        .down(KeyCode::KeyJ)
+        .down(KeyCode::KeyJ)
+        .up(KeyCode::KeyJ)
+        .up(KeyCode::AltRight);
+    listener.play(&script);
+    assert_eq!(
+        passed_keys(&mut listener),
+        vec![
+            (KeyCode::AltRight, KeyOpt::Down),
+            (KeyCode::AltRight, KeyOpt::Up),
+            (KeyCode::LeftArrow, KeyOpt::Down),
+            (KeyCode::LeftArrow, KeyOpt::Down),
+            (KeyCode::LeftArrow, KeyOpt::Up),
+            (KeyCode::AltRight, KeyOpt::Down),
+            (KeyCode::AltRight, KeyOpt::Up),
+        ]
+    );
+
+    // the modifer is released first, and not released twice
+    let script = Script::new()
+        .down(KeyCode::AltRight)
+        .down(KeyCode::KeyJ)
+        .up(KeyCode::AltRight)
+        .up(KeyCode::KeyJ);
+    listener.play(&script);
+    assert_eq!(
+        passed_keys(&mut listener),
+        vec![
+            (KeyCode::AltRight, KeyOpt::Down),
+            (KeyCode::AltRight, KeyOpt::Up),
+            (KeyCode::LeftArrow, KeyOpt::Down),
+            (KeyCode::LeftArrow, KeyOpt::Up),
+        ]
+    );
+}
+
+#[test]
+fn target_modifers_are_pressed_and_released() {
+    let mut listener = ScriptedListener::new();
+    remap(&mut listener, "F1", "Ctrl+Shift+T");
+    listener.play(&Script::new().tap(KeyCode::F1));
+    assert_eq!(
+        passed_keys(&mut listener),
+        vec![
+            (KeyCode::ControlLeft, KeyOpt::Down),
+            (KeyCode::ShiftLeft, KeyOpt::Down),
+            (KeyCode::KeyT, KeyOpt::Down),
+            (KeyCode::KeyT, KeyOpt::Up),
+            (KeyCode::ShiftLeft, KeyOpt::Up),
+            (KeyCode::ControlLeft, KeyOpt::Up),
+        ]
+    );
+}
+
+#[test]
+fn unremap_releases_held_keys() {
+    let mut listener = ScriptedListener::new();
+    let uid = remap(&mut listener, "CapsLock", "LCtrl");
+    listener.play(&Script::new().down(KeyCode::Capslock));
+    listener.proxy().unremap(uid).unwrap();
+    listener.play(&Script::new().up(KeyCode::Capslock).tap(KeyCode::Capslock));
+    assert_eq!(
+        passed_keys(&mut listener),
+        vec![
+            (KeyCode::ControlLeft, KeyOpt::Down),
+            (KeyCode::ControlLeft, KeyOpt::Up),
+            (KeyCode::Capslock, KeyOpt::Up),
+            (KeyCode::Capslock, KeyOpt::Down),
+            (KeyCode::Capslock, KeyOpt::Up),
+        ]
+    );
+}
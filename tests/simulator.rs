@@ -0,0 +1,101 @@
+use inbot::*;
+
+fn recording_simulator() -> (Simulator, RecordingBackend) {
+    let backend = RecordingBackend::new();
+    (Simulator::with_backend(Box::new(backend.clone())), backend)
+}
+
+fn key_event(key: KeyCode, opt: KeyOpt) -> SimulatedEvent {
+    SimulatedEvent::Key { key, opt }
+}
+
+#[test]
+fn press_release_and_tap() {
+    let (mut simulator, backend) = recording_simulator();
+    simulator.press(KeyCode::ShiftLeft).unwrap();
+    simulator.tap(KeyCode::KeyA).unwrap();
+    simulator.release(KeyCode::ShiftLeft).unwrap();
+    assert_eq!(
+        backend.take_events(),
+        vec![
+            key_event(KeyCode::ShiftLeft, KeyOpt::Down),
+            key_event(KeyCode::KeyA, KeyOpt::Down),
+            key_event(KeyCode::KeyA, KeyOpt::Up),
+            key_event(KeyCode::ShiftLeft, KeyOpt::Up),
+        ]
+    );
+    assert!(backend.get_events().is_empty());
+}
+
+#[test]
+fn binding_key_releases_modifers_in_reverse_order() {
+    let (mut simulator, backend) = recording_simulator();
+    simulator
+        .tap_binding_key(&BindingKey {
+            key: KeyCode::KeyK,
+            modifer_keys: vec![KeyCode::ControlLeft, KeyCode::ShiftLeft],
+        })
+        .unwrap();
+    assert_eq!(
+        backend.get_events(),
+        vec![
+            key_event(KeyCode::ControlLeft, KeyOpt::Down),
+            key_event(KeyCode::ShiftLeft, KeyOpt::Down),
+            key_event(KeyCode::KeyK, KeyOpt::Down),
+            key_event(KeyCode::KeyK, KeyOpt::Up),
+            key_event(KeyCode::ShiftLeft, KeyOpt::Up),
+            key_event(KeyCode::ControlLeft, KeyOpt::Up),
+        ]
+    );
+}
+
+#[test]
+fn mouse_moves_clicks_and_scrolls() {
+    let (mut simulator, backend) = recording_simulator();
+    let pos = CursorPos { x: 10, y: 20 };
+    simulator.click_at(pos, KeyCode::MouseRight).unwrap();
+    simulator.scroll(0, -3).unwrap();
+    assert_eq!(
+        backend.get_events(),
+        vec![
+            SimulatedEvent::MouseMove(pos),
+            key_event(KeyCode::MouseRight, KeyOpt::Down),
+            key_event(KeyCode::MouseRight, KeyOpt::Up),
+            SimulatedEvent::Scroll {
+                delta_x: 0,
+                delta_y: -3
+            },
+        ]
+    );
+}
+
+#[test]
+fn simulated_chord_triggers_its_binding() {
+    let binding_key = BindingKey {
+        key: KeyCode::KeyK,
+        modifer_keys: vec![KeyCode::ControlLeft, KeyCode::ShiftLeft],
+    };
+    let (mut simulator, backend) = recording_simulator();
+    simulator.tap_binding_key(&binding_key).unwrap();
+
+    let mut listener = ScriptedListener::new();
+    let uid = listener
+        .proxy()
+        .bind_multi(vec![binding_key], Box::new(|| {}))
+        .unwrap()
+        .detach();
+    let script =
+        backend
+            .get_events()
+            .into_iter()
+            .fold(Script::new(), |script, event| match event {
+                SimulatedEvent::Key {
+                    key,
+                    opt: KeyOpt::Down,
+                } => script.down(key),
+                SimulatedEvent::Key { key, .. } => script.up(key),
+                _ => script,
+            });
+    listener.play(&script);
+    listener.assert_triggered(uid, 1);
+}
@@ -0,0 +1,159 @@
+use inbot::*;
+use std::collections::HashSet;
+
+/// keys with a Windows virtual-key code only, that convert as evdev code 0
+const KEYS_WITHOUT_EVDEV_CODE: [KeyCode; 33] = [
+    KeyCode::Execute,
+    KeyCode::Junja,
+    KeyCode::Final,
+    KeyCode::ImeOn,
+    KeyCode::ImeOff,
+    KeyCode::Accept,
+    KeyCode::ModeChange,
+    KeyCode::ProcessKey,
+    KeyCode::Oem8,
+    KeyCode::Attn,
+    KeyCode::CrSel,
+    KeyCode::ExSel,
+    KeyCode::EraseEof,
+    KeyCode::Pa1,
+    KeyCode::OemClear,
+    KeyCode::OemAx,
+    KeyCode::AbntC2,
+    KeyCode::IcoHelp,
+    KeyCode::Ico00,
+    KeyCode::IcoClear,
+    KeyCode::OemReset,
+    KeyCode::OemJump,
+    KeyCode::OemPa1,
+    KeyCode::OemPa2,
+    KeyCode::OemPa3,
+    KeyCode::OemWsCtrl,
+    KeyCode::OemCuSel,
+    KeyCode::OemAttn,
+    KeyCode::OemFinish,
+    KeyCode::OemCopy,
+    KeyCode::OemAuto,
+    KeyCode::OemEnlw,
+    KeyCode::OemBackTab,
+];
+
+/// keys that no US keyboard has a scan code for, mouse buttons included
+const KEYS_WITHOUT_SCAN_CODE: [KeyCode; 12] = [
+    KeyCode::Cancel,
+    KeyCode::Clear,
+    KeyCode::Select,
+    KeyCode::Print,
+    KeyCode::Help,
+    KeyCode::Play,
+    KeyCode::Zoom,
+    KeyCode::MouseLeft,
+    KeyCode::MouseRight,
+    KeyCode::MouseMiddle,
+    KeyCode::MouseX1,
+    KeyCode::MouseX2,
+];
+
+/// keys that a backend has no code for, and that convert as 0
+fn keys_without_code(to_code: fn(&KeyCode) -> u32) -> Vec<KeyCode> {
+    KeyCode::known_keys()
+        .filter(|key| !key.is_wheel() && to_code(key) == 0)
+        .collect()
+}
+
+/// every key but `skipped_keys` has a code of its own that converts back to it
+fn assert_round_trips(
+    to_code: fn(&KeyCode) -> u32,
+    from_code: fn(u32) -> KeyCode,
+    skipped_keys: &[KeyCode],
+) {
+    let mut codes = HashSet::new();
+    for key in KeyCode::known_keys().filter(|key| !key.is_either_side() && !key.is_wheel()) {
+        if skipped_keys.contains(&key) {
+            continue;
+        }
+        let code = to_code(&key);
+        assert!(codes.insert(code), "{:?} shares code {}", key, code);
+        assert_eq!(from_code(code), key);
+    }
+}
+
+#[test]
+fn windows_ids_round_trip() {
+    // Windows reports the numpad Enter as `Enter` with the extended-key flag
+    assert_round_trips(
+        KeyCode::to_windows_id,
+        KeyCode::from_windows_id,
+        &[KeyCode::NumpadEnter],
+    );
+    assert_eq!(KeyCode::from_windows_id(0xFF), KeyCode::Unknown(0xFF));
+    assert_eq!(KeyCode::NumpadEnter.to_windows_id(), 0x0D);
+}
+
+#[test]
+fn evdev_codes_round_trip() {
+    assert_round_trips(
+        KeyCode::to_evdev_code,
+        KeyCode::from_evdev_code,
+        &KEYS_WITHOUT_EVDEV_CODE,
+    );
+    assert_eq!(KeyCode::from_evdev_code(0x2ff), KeyCode::Unknown(0x2ff));
+}
+
+#[test]
+fn backends_miss_only_the_listed_keys() {
+    assert_eq!(keys_without_code(KeyCode::to_windows_id), vec![]);
+    assert_eq!(
+        keys_without_code(KeyCode::to_evdev_code),
+        KEYS_WITHOUT_EVDEV_CODE
+    );
+    let mut keys_without_scan_code = KEYS_WITHOUT_EVDEV_CODE.to_vec();
+    keys_without_scan_code.extend(KEYS_WITHOUT_SCAN_CODE);
+    let mut missing = keys_without_code(KeyCode::to_scan_code);
+    missing.sort_by_key(|key| key.to_windows_id());
+    keys_without_scan_code.sort_by_key(|key| key.to_windows_id());
+    assert_eq!(missing, keys_without_scan_code);
+}
+
+#[test]
+fn numpad_media_and_function_keys_are_known() {
+    let expected = [
+        (KeyCode::Numpad0, 0x60, 82),
+        (KeyCode::Numpad9, 0x69, 73),
+        (KeyCode::NumpadDivide, 0x6F, 98),
+        (KeyCode::NumLock, 0x90, 69),
+        (KeyCode::F13, 0x7C, 183),
+        (KeyCode::F24, 0x87, 194),
+        (KeyCode::VolumeUp, 0xAF, 115),
+        (KeyCode::MediaPlayPause, 0xB3, 164),
+        (KeyCode::BrowserBack, 0xA6, 158),
+        (KeyCode::ContextMenu, 0x5D, 127),
+        (KeyCode::IntlBackslash, 0xE2, 86),
+    ];
+    for (key, windows_id, evdev_code) in expected {
+        assert_eq!(key.to_windows_id(), windows_id, "{:?}", key);
+        assert_eq!(key.to_evdev_code(), evdev_code, "{:?}", key);
+    }
+}
+
+#[test]
+fn every_key_name_parses_back() {
+    for key in KeyCode::known_keys() {
+        assert_eq!(key.to_name().parse::<KeyCode>().unwrap(), key);
+    }
+}
+
+#[test]
+fn scan_codes_round_trip() {
+    let mut keys_without_scan_code = KEYS_WITHOUT_EVDEV_CODE.to_vec();
+    keys_without_scan_code.extend(KEYS_WITHOUT_SCAN_CODE);
+    assert_round_trips(
+        KeyCode::to_scan_code,
+        KeyCode::from_scan_code,
+        &keys_without_scan_code,
+    );
+    assert_eq!(KeyCode::KeyQ.to_scan_code(), 0x10);
+    assert_eq!(KeyCode::NumpadEnter.to_scan_code(), 0xE01C);
+    assert_eq!(KeyCode::Enter.to_scan_code(), 0x1C);
+    assert_eq!(KeyCode::MouseLeft.to_scan_code(), 0);
+}